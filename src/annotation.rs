@@ -0,0 +1,76 @@
+use crate::contig::{ContigNames, ContigStyle};
+use crate::variant::Variant;
+use rust_htslib::bcf::{IndexedReader, Read, Record};
+use rust_htslib::errors::Result;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Indexed VCF file of known variants, such as ClinVar, gnomAD or dbSNP,
+/// whose records are looked up by position and alleles.
+pub struct AnnotationFile {
+    reader: IndexedReader,
+}
+
+impl AnnotationFile {
+    pub fn open(path: &Path) -> Result<Self> {
+        Ok(AnnotationFile {
+            reader: IndexedReader::from_path(path)?,
+        })
+    }
+
+    /// Contig of the file named like contig, in UCSC or Ensembl style.
+    fn rid(&self, contig: &str) -> Option<u32> {
+        let header = self.reader.header();
+        [None, Some(ContigStyle::Ucsc), Some(ContigStyle::Ensembl)]
+            .into_iter()
+            .map(|style| ContigNames::new(style, HashMap::new()).rename(contig))
+            .find_map(|name| header.name2rid(name.as_bytes()).ok())
+    }
+
+    /// Record with the position and REF of the variant and its first ALT
+    /// allele, with the index of that allele among the ALT alleles of the
+    /// record.
+    pub fn find(&mut self, v: &Variant) -> Result<Option<(Record, usize)>> {
+        let (rid, alt) = match (self.rid(&v.reference_name), v.alternate_bases.first()) {
+            (Some(rid), Some(alt)) => (rid, alt.as_bytes()),
+            _ => return Ok(None),
+        };
+        let start = v.start as u64 - 1;
+        if self.reader.fetch(rid, start, Some(start)).is_err() {
+            // No record on this contig.
+            return Ok(None);
+        }
+
+        let mut record = self.reader.empty_record();
+        while let Some(result) = self.reader.read(&mut record) {
+            result?;
+            if record.pos() as u64 != start {
+                continue;
+            }
+            let alleles = record.alleles();
+            if !alleles[0].eq_ignore_ascii_case(v.reference_bases.as_bytes()) {
+                continue;
+            }
+            if let Some(i) = alleles[1..]
+                .iter()
+                .position(|x| x.eq_ignore_ascii_case(alt))
+            {
+                return Ok(Some((record, i)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// IDs of the record of the same variant, such as dbSNP rsIDs.
+    pub fn ids(&mut self, v: &Variant) -> Result<Option<Vec<String>>> {
+        let record = match self.find(v)? {
+            Some((record, _)) => record,
+            None => return Ok(None),
+        };
+        let id = String::from_utf8_lossy(&record.id()).to_string();
+        if id == "." {
+            return Ok(None);
+        }
+        Ok(Some(id.split(';').map(|x| x.to_string()).collect()))
+    }
+}
@@ -0,0 +1,23 @@
+use reqwest::blocking::RequestBuilder;
+
+/// Credentials sent with every request to the server.
+pub enum Auth {
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+    /// `Authorization: Bearer <token>`, e.g. for servers behind an API gateway.
+    Bearer(String),
+    /// Token sent as is in a custom header, such as `X-API-Key`.
+    ApiKey { header: String, key: String },
+}
+
+impl Auth {
+    pub fn apply(&self, req: RequestBuilder) -> RequestBuilder {
+        match self {
+            Auth::Basic { username, password } => req.basic_auth(username, password.as_ref()),
+            Auth::Bearer(token) => req.bearer_auth(token),
+            Auth::ApiKey { header, key } => req.header(header.as_str(), key.as_str()),
+        }
+    }
+}
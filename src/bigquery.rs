@@ -0,0 +1,82 @@
+use crate::schema::{resolve, types};
+use crate::variant::Variant;
+use schemars::schema_for;
+use serde_json::{json, Map, Value};
+
+/// BigQuery table schema of the variants written by export, derived from
+/// their JSON Schema, for `bq load --source_format=NEWLINE_DELIMITED_JSON`.
+/// Objects with arbitrary keys, such as scores, become JSON columns.
+pub fn schema() -> Value {
+    let root = serde_json::to_value(schema_for!(Variant)).unwrap();
+    let definitions = root.get("definitions").cloned().unwrap_or_default();
+    Value::Array(fields(&root, &definitions))
+}
+
+fn fields(schema: &Value, definitions: &Value) -> Vec<Value> {
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .map(|x| x.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    schema["properties"]
+        .as_object()
+        .map(|properties| {
+            properties
+                .iter()
+                .map(|(name, property)| {
+                    field(
+                        name,
+                        property,
+                        definitions,
+                        required.contains(&name.as_str()),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn field(name: &str, schema: &Value, definitions: &Value, required: bool) -> Value {
+    let (schema, nullable) = resolve(schema, definitions);
+    let kinds = types(&schema);
+    let nullable = nullable || kinds.contains(&"null");
+    let mode = if kinds.contains(&"array") {
+        "REPEATED"
+    } else if required && !nullable {
+        "REQUIRED"
+    } else {
+        "NULLABLE"
+    };
+    let schema = if kinds.contains(&"array") {
+        resolve(&schema["items"], definitions).0
+    } else {
+        schema
+    };
+
+    let mut field = Map::new();
+    field.insert("name".to_string(), json!(name));
+    field.insert("mode".to_string(), json!(mode));
+    if let Some(description) = schema.get("description") {
+        field.insert("description".to_string(), description.clone());
+    }
+    let kinds = types(&schema);
+    let bq_type = if kinds.contains(&"object") {
+        if schema.get("properties").is_some() {
+            field.insert("fields".to_string(), json!(fields(&schema, definitions)));
+            "RECORD"
+        } else {
+            "JSON"
+        }
+    } else if kinds.contains(&"integer") {
+        "INTEGER"
+    } else if kinds.contains(&"number") {
+        "FLOAT"
+    } else if kinds.contains(&"boolean") {
+        "BOOLEAN"
+    } else if kinds.contains(&"string") {
+        "STRING"
+    } else {
+        "JSON"
+    };
+    field.insert("type".to_string(), json!(bq_type));
+    Value::Object(field)
+}
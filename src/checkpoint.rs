@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Last record of a batch, identified by its ordinal in the VCF file and by
+/// its coordinates, so a resumed import can check it is reading the same file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Position {
+    pub record: u64,
+    #[serde(rename = "referenceName")]
+    pub reference_name: String,
+    pub start: i64,
+}
+
+struct Progress {
+    next: u64,
+    pending: BTreeMap<u64, Position>,
+}
+
+/// Tracks uploaded batches and persists the position of the last batch
+/// after which every batch was uploaded. Batches finish out of order when
+/// several workers are used, so later ones are held back until the gap is
+/// filled.
+pub struct Checkpoint {
+    path: PathBuf,
+    progress: Mutex<Progress>,
+}
+
+impl Checkpoint {
+    pub fn new(path: PathBuf) -> Self {
+        Checkpoint {
+            path,
+            progress: Mutex::new(Progress {
+                next: 0,
+                pending: BTreeMap::new(),
+            }),
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Option<Position>> {
+        match fs::read(path) {
+            Ok(content) => Ok(Some(serde_json::from_slice(&content)?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Mark batch number `seq` as uploaded.
+    pub fn complete(&self, seq: u64, position: Position) {
+        let mut guard = self.progress.lock().unwrap();
+        let progress = &mut *guard;
+        progress.pending.insert(seq, position);
+
+        let mut last = None;
+        while let Some(position) = progress.pending.remove(&progress.next) {
+            progress.next += 1;
+            last = Some(position);
+        }
+
+        if let Some(position) = last {
+            self.save(&position)
+                .unwrap_or_else(|e| panic!("Error writing checkpoint {:?}: {}", self.path, e));
+        }
+    }
+
+    fn save(&self, position: &Position) -> io::Result<()> {
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec(position)?)?;
+        fs::rename(&tmp, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(record: u64) -> Position {
+        Position {
+            record,
+            reference_name: "1".to_string(),
+            start: record as i64 * 100,
+        }
+    }
+
+    #[test]
+    fn batches_finished_out_of_order_wait_for_earlier_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("import.checkpoint");
+        let checkpoint = Checkpoint::new(path.clone());
+
+        checkpoint.complete(1, position(20));
+        checkpoint.complete(2, position(30));
+        assert_eq!(Checkpoint::load(&path).unwrap(), None);
+
+        checkpoint.complete(0, position(10));
+        assert_eq!(Checkpoint::load(&path).unwrap(), Some(position(30)));
+
+        checkpoint.complete(4, position(50));
+        assert_eq!(Checkpoint::load(&path).unwrap(), Some(position(30)));
+        checkpoint.complete(3, position(40));
+        assert_eq!(Checkpoint::load(&path).unwrap(), Some(position(50)));
+    }
+}
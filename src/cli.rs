@@ -0,0 +1,1361 @@
+use crate::annotation::AnnotationFile;
+use crate::auth::Auth;
+use crate::checkpoint::{Checkpoint, Position};
+use crate::config::Config;
+use crate::consequence::{ConsequenceFilter, Impact};
+use crate::contig::{read_map, ContigNames, ContigStyle};
+use crate::elasticsearch::Elasticsearch;
+use crate::exit::Exit;
+use crate::export::ExportFormat;
+use crate::fasta::Fasta;
+use crate::kafka::{Acks, Kafka, MessageKey};
+use crate::ledger::Ledger;
+use crate::liftover::Chain;
+use crate::logger::LogFormat;
+use crate::login;
+use crate::mapping::Mapping;
+use crate::mongodb::MongoDb;
+use crate::preflight::ApiVersion;
+use crate::privacy::{Privacy, PrivacyMode};
+use crate::progress::Progress;
+use crate::pseudonym::Pseudonyms;
+use crate::reader::remote_url;
+use crate::region::{read_bed_file, read_regions_file, Region, RegionSet};
+use crate::samples::SampleFilter;
+use crate::transcript::{read_transcripts, TranscriptPolicy, TranscriptSource, Transcripts};
+use crate::upload::{Mode, RateLimiter, RetryPolicy, Sink, Uploader, WireFormat, WorkerPool};
+use crate::variant::Aggregation;
+use clap::{ArgEnum, Args, Parser, Subcommand};
+use glob::glob;
+use log::{info, warn, LevelFilter};
+use reqwest::blocking::{Client, ClientBuilder};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Certificate, Identity, Proxy};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[clap(version = "0.1.0", author = "Welliton de Souza <well309@gmail.com>")]
+pub struct Opts {
+    #[clap(
+        long,
+        global = true,
+        default_value = "info",
+        possible_values = &["off", "error", "warn", "info", "debug", "trace"],
+        help = "Only log messages of this level or more severe"
+    )]
+    pub log_level: LevelFilter,
+    #[clap(
+        long,
+        global = true,
+        help = "Append log messages to this file instead of stderr"
+    )]
+    pub log_file: Option<PathBuf>,
+    #[clap(
+        long,
+        global = true,
+        arg_enum,
+        default_value = "text",
+        help = "Write log messages as text or as one JSON object per line"
+    )]
+    pub log_format: LogFormat,
+    #[clap(
+        long,
+        global = true,
+        help = "Read default options from this TOML file [default: ~/.config/brave-import/config.toml]"
+    )]
+    pub config: Option<PathBuf>,
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[clap(about = "Import variants from VCF file into BraVE server")]
+    Import(Box<ImportOpts>),
+    #[clap(about = "Check that every variant of VCF file can be imported")]
+    Validate(ValidateOpts),
+    #[clap(about = "Write variants as JSON instead of sending them to server")]
+    Export(ExportOpts),
+    #[clap(about = "Send variants previously written by export to BraVE server")]
+    UploadNdjson(UploadNdjsonOpts),
+    #[clap(about = "Delete variants of a dataset from BraVE server")]
+    Delete(DeleteOpts),
+    #[clap(about = "Print statistics about VCF file")]
+    Stats(StatsOpts),
+    #[clap(about = "Save password for BraVE server in the OS keyring")]
+    Login(LoginOpts),
+    #[clap(about = "Compare variants of VCF file with those stored on BraVE server")]
+    Diff(Box<DiffOpts>),
+    #[clap(about = "Print variants stored on BraVE server")]
+    Query(QueryOpts),
+    #[clap(about = "Print JSON Schema of variants sent to BraVE server")]
+    Schema,
+    #[clap(about = "Create or update dataset metadata on BraVE server from TOML file")]
+    Dataset(DatasetMetadataOpts),
+    #[clap(about = "Import VCF files as they appear in a directory")]
+    Watch(WatchOpts),
+    #[clap(about = "Accept VCF files over HTTP and import them in the background")]
+    Serve(ServeOpts),
+}
+
+impl Command {
+    /// Apply config file values to the options of the subcommand.
+    pub fn merge(&mut self, config: &Config) {
+        match self {
+            Command::Import(opts) => {
+                opts.server.merge(config);
+                opts.input.merge(config);
+            }
+            Command::Validate(opts) => opts.input.merge(config),
+            Command::Export(opts) => opts.input.merge(config),
+            Command::UploadNdjson(opts) => opts.server.merge(config),
+            Command::Delete(opts) => opts.server.merge(config),
+            Command::Stats(_) | Command::Schema | Command::Watch(_) | Command::Serve(_) => {}
+            Command::Login(opts) => opts.server.merge(config),
+            Command::Query(opts) => opts.server.merge(config),
+            Command::Dataset(opts) => opts.server.merge(config),
+            Command::Diff(opts) => {
+                opts.server.merge(config);
+                opts.input.merge(config);
+            }
+        }
+    }
+
+    /// Options of the server the subcommand connects to, if any.
+    pub fn server(&mut self) -> Option<&mut ServerOpts> {
+        match self {
+            Command::Import(opts) if !opts.dryrun && opts.upload.uses_server() => {
+                Some(&mut opts.server)
+            }
+            Command::UploadNdjson(opts) if opts.upload.uses_server() => Some(&mut opts.server),
+            Command::Delete(opts) => Some(&mut opts.server),
+            Command::Diff(opts) => Some(&mut opts.server),
+            Command::Query(opts) => Some(&mut opts.server),
+            Command::Dataset(opts) => Some(&mut opts.server),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ServerOpts {
+    #[clap(
+        long,
+        env = "BRAVE_HOST",
+        help = "URL to BraVE server [default: http://localhost:8080]"
+    )]
+    pub host: Option<String>,
+    #[clap(long, env = "BRAVE_USERNAME", help = "User name [default: admin]")]
+    pub username: Option<String>,
+    #[clap(
+        long,
+        env = "BRAVE_PASSWORD",
+        hide_env_values = true,
+        help = "Password"
+    )]
+    pub password: Option<String>,
+    #[clap(
+        long,
+        env = "BRAVE_TOKEN",
+        hide_env_values = true,
+        conflicts_with = "token-file",
+        help = "Send this bearer token instead of user name and password"
+    )]
+    pub token: Option<String>,
+    #[clap(long, help = "Read bearer token from this file")]
+    pub token_file: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "Send token in this header, e.g. X-API-Key, instead of Authorization: Bearer"
+    )]
+    pub api_key_header: Option<String>,
+    #[clap(
+        long,
+        parse(try_from_str = parse_header),
+        help = "Add header to every request, e.g. 'X-Tenant: oncology' (repeatable)"
+    )]
+    pub header: Vec<(HeaderName, HeaderValue)>,
+    #[clap(
+        long,
+        default_value = "10",
+        help = "Seconds to wait for the connection to the server"
+    )]
+    pub connect_timeout: f64,
+    #[clap(
+        long,
+        default_value = "300",
+        help = "Seconds to wait for the server to respond to a request"
+    )]
+    pub request_timeout: f64,
+    #[clap(
+        long,
+        help = "Don't check server health and credentials before starting"
+    )]
+    pub skip_preflight: bool,
+    /// API version reported by the server during preflight.
+    #[clap(skip)]
+    pub api_version: Option<ApiVersion>,
+    #[clap(long, help = "Disable SSL certification verification")]
+    pub disable_ssl: bool,
+    #[clap(
+        long,
+        help = "Also trust certificates signed by this PEM CA certificate"
+    )]
+    pub ca_cert: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "Connect through this proxy, e.g. http://proxy:3128 or socks5://proxy:1080"
+    )]
+    pub proxy: Option<String>,
+    #[clap(long, help = "User name for proxy authentication")]
+    pub proxy_username: Option<String>,
+    #[clap(
+        long,
+        env = "BRAVE_PROXY_PASSWORD",
+        hide_env_values = true,
+        help = "Password for proxy authentication"
+    )]
+    pub proxy_password: Option<String>,
+    #[clap(
+        long,
+        help = "Client certificate for mutual TLS, PEM or PKCS#12 (.p12, .pfx)"
+    )]
+    pub client_cert: Option<PathBuf>,
+    #[clap(long, help = "Private key of PEM client certificate")]
+    pub client_key: Option<PathBuf>,
+    #[clap(
+        long,
+        env = "BRAVE_CLIENT_CERT_PASSWORD",
+        hide_env_values = true,
+        help = "Password of PKCS#12 client certificate"
+    )]
+    pub client_cert_password: Option<String>,
+}
+
+impl ServerOpts {
+    /// Fill options missing from the command line with config file values.
+    pub fn merge(&mut self, config: &Config) {
+        self.host = self.host.take().or_else(|| config.host.clone());
+        self.username = self.username.take().or_else(|| config.username.clone());
+        self.password = self.password.take().or_else(|| config.password.clone());
+        if self.token.is_none() && self.token_file.is_none() {
+            self.token = config.token.clone();
+            self.token_file = config.token_file.clone();
+        }
+        self.api_key_header = self
+            .api_key_header
+            .take()
+            .or_else(|| config.api_key_header.clone());
+        self.disable_ssl |= config.disable_ssl;
+        let headers = config.header.iter().map(|header| {
+            parse_header(header).unwrap_or_else(|e| panic!("Invalid config header: {}", e))
+        });
+        // Headers given on the command line come last so they win.
+        self.header.splice(0..0, headers);
+        self.proxy = self.proxy.take().or_else(|| config.proxy.clone());
+        self.proxy_username = self
+            .proxy_username
+            .take()
+            .or_else(|| config.proxy_username.clone());
+        self.ca_cert = self.ca_cert.take().or_else(|| config.ca_cert.clone());
+        self.client_cert = self
+            .client_cert
+            .take()
+            .or_else(|| config.client_cert.clone());
+        self.client_key = self.client_key.take().or_else(|| config.client_key.clone());
+    }
+
+    /// Look up the password saved by `login` when it wasn't given.
+    pub fn load_password(&mut self) {
+        if self.password.is_none() && !self.has_token() {
+            self.password = login::stored_password(self.host(), self.username());
+        }
+    }
+
+    /// Ask for the password when it wasn't given and stdin is a terminal,
+    /// instead of sending requests the server would reject.
+    pub fn prompt_password(&mut self) {
+        if self.password.is_some() || self.has_token() || !io::stdin().is_terminal() {
+            return;
+        }
+        let prompt = format!("Password for {} at {}: ", self.username(), self.host());
+        let password = rpassword::prompt_password(prompt)
+            .unwrap_or_else(|e| panic!("Error reading password: {}", e));
+        self.password = Some(password);
+    }
+
+    fn has_token(&self) -> bool {
+        self.token.is_some() || self.token_file.is_some()
+    }
+
+    pub fn auth(&self) -> Auth {
+        let token = match (&self.token, &self.token_file) {
+            (Some(token), _) => token.clone(),
+            (None, Some(path)) => fs::read_to_string(path)
+                .unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e))
+                .trim()
+                .to_string(),
+            (None, None) => {
+                return Auth::Basic {
+                    username: self.username().to_string(),
+                    password: self.password.clone(),
+                }
+            }
+        };
+        match &self.api_key_header {
+            Some(header) => Auth::ApiKey {
+                header: header.clone(),
+                key: token,
+            },
+            None => Auth::Bearer(token),
+        }
+    }
+
+    pub fn host(&self) -> &str {
+        self.host.as_deref().unwrap_or("http://localhost:8080")
+    }
+
+    pub fn username(&self) -> &str {
+        self.username.as_deref().unwrap_or("admin")
+    }
+
+    pub fn client(&self) -> Client {
+        let headers: HeaderMap = self.header.iter().cloned().collect();
+        let mut builder = self.client_builder().default_headers(headers);
+        if let Some(identity) = self.identity() {
+            builder = builder.identity(identity);
+        }
+        builder.build().unwrap()
+    }
+
+    /// Client for other services than the BraVE server, with the timeouts,
+    /// TLS and proxy settings, but without the headers and client
+    /// certificate meant for the server.
+    pub fn transport_client(&self) -> Client {
+        self.client_builder().build().unwrap()
+    }
+
+    fn client_builder(&self) -> ClientBuilder {
+        let mut builder = Client::builder()
+            .connect_timeout(Duration::from_secs_f64(self.connect_timeout))
+            .timeout(Duration::from_secs_f64(self.request_timeout))
+            .danger_accept_invalid_certs(self.disable_ssl);
+        if let Some(path) = &self.ca_cert {
+            let pem = fs::read(path).unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e));
+            let cert = Certificate::from_pem(&pem)
+                .unwrap_or_else(|e| panic!("Invalid CA certificate {:?}: {}", path, e));
+            builder = builder.add_root_certificate(cert);
+        }
+        if let Some(url) = &self.proxy {
+            let mut proxy =
+                Proxy::all(url).unwrap_or_else(|e| panic!("Invalid proxy {}: {}", url, e));
+            if let Some(username) = &self.proxy_username {
+                let password = self.proxy_password.as_deref().unwrap_or("");
+                proxy = proxy.basic_auth(username, password);
+            }
+            builder = builder.proxy(proxy);
+        }
+        builder
+    }
+
+    /// Client certificate, read from a PKCS#12 archive or from PEM files.
+    fn identity(&self) -> Option<Identity> {
+        let path = self.client_cert.as_ref()?;
+        let read = |path: &PathBuf| {
+            fs::read(path).unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e))
+        };
+        let cert = read(path);
+
+        let is_pkcs12 = path.extension().is_some_and(|x| x == "p12" || x == "pfx");
+        let identity = if is_pkcs12 {
+            let password = self.client_cert_password.as_deref().unwrap_or("");
+            Identity::from_pkcs12_der(&cert, password)
+        } else {
+            let key = self
+                .client_key
+                .as_ref()
+                .unwrap_or_else(|| panic!("--client-key is required with PEM certificate"));
+            Identity::from_pkcs8_pem(&cert, &read(key))
+        };
+        Some(identity.unwrap_or_else(|e| panic!("Invalid client certificate {:?}: {}", path, e)))
+    }
+}
+
+/// Parse a header written as `Name: value`.
+fn parse_header(s: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("missing colon in header {}", s))?;
+    let name = HeaderName::try_from(name.trim()).map_err(|e| format!("{}: {}", s, e))?;
+    let value = HeaderValue::try_from(value.trim()).map_err(|e| format!("{}: {}", s, e))?;
+    Ok((name, value))
+}
+
+fn parse_percentile(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(p) if p <= 100 => Ok(p),
+        _ => Err(format!("{} is not a percentile between 0 and 100", s)),
+    }
+}
+
+fn parse_aggregation(s: &str) -> Result<(String, Aggregation), String> {
+    let (tag, aggregation) = s
+        .split_once('=')
+        .ok_or_else(|| format!("missing = in {}", s))?;
+    let aggregation = Aggregation::from_str(aggregation, true)?;
+    Ok((tag.to_string(), aggregation))
+}
+
+#[derive(Args)]
+pub struct DatasetOpts {
+    #[clap(long, help = "Dataset name")]
+    pub dataset: String,
+    #[clap(long, help = "Genome assembly version")]
+    pub assembly: String,
+}
+
+#[derive(ArgEnum, Clone, Copy, PartialEq)]
+pub enum StarAlleles {
+    Drop,
+    Keep,
+}
+
+#[derive(Args)]
+pub struct InputOpts {
+    #[clap(long, help = "Don't filter variants by FILTER column")]
+    pub dont_filter: bool,
+    #[clap(
+        long,
+        conflicts_with = "lenient",
+        help = "Stop at the first record that can't be imported, and require INFO/AF"
+    )]
+    pub strict: bool,
+    #[clap(long, help = "Skip and log records that can't be imported (default)")]
+    pub lenient: bool,
+    #[clap(
+        long,
+        help = "Only read variants in region chr:start-end, requires an indexed file"
+    )]
+    pub region: Vec<Region>,
+    #[clap(
+        long,
+        help = "Only read variants in regions listed in file (CHROM, BEG, END or BED)"
+    )]
+    pub regions_file: Option<PathBuf>,
+    #[clap(long, help = "Skip variants outside the intervals of BED file")]
+    pub bed: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "Skip variants not annotated with a gene listed in file, one symbol per line"
+    )]
+    pub genes: Option<PathBuf>,
+    #[clap(
+        long,
+        use_value_delimiter = true,
+        help = "Compute statistics from these samples only, e.g. s1,s2"
+    )]
+    pub samples: Vec<String>,
+    #[clap(
+        long,
+        help = "Compute statistics from the samples listed in file only, one per line"
+    )]
+    pub samples_file: Option<PathBuf>,
+    #[clap(
+        long,
+        use_value_delimiter = true,
+        help = "Leave these samples out of statistics, e.g. s3,s4"
+    )]
+    pub exclude_samples: Vec<String>,
+    #[clap(
+        long,
+        env = "BRAVE_PSEUDONYM_SALT",
+        hide_env_values = true,
+        conflicts_with = "pseudonym-map",
+        help = "Show sample IDs as salted hashes in logs and output"
+    )]
+    pub pseudonym_salt: Option<String>,
+    #[clap(
+        long,
+        help = "Show sample IDs as the pseudonyms of this file of tab-separated IDs and pseudonyms"
+    )]
+    pub pseudonym_map: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "VCF arguments are htsget endpoints, e.g. https://server/variants/ID"
+    )]
+    pub htsget: bool,
+    #[clap(
+        long,
+        default_value = "0",
+        help = "Number of extra threads decompressing VCF files"
+    )]
+    pub decompress_threads: usize,
+    #[clap(
+        long,
+        help = "Skip variants whose REF does not match this indexed FASTA file"
+    )]
+    pub reference: Option<PathBuf>,
+    #[clap(
+        long,
+        requires = "reference",
+        help = "Trim shared bases and left-align indels using the reference"
+    )]
+    pub normalize: bool,
+    #[clap(
+        long,
+        requires = "reference",
+        help = "Send GA4GH VRS identifiers of ALT alleles, computed with the reference"
+    )]
+    pub vrs: bool,
+    #[clap(
+        long,
+        requires = "reference",
+        help = "Send canonical SPDI of ALT alleles, with the RefSeq accessions of contigs listed in file"
+    )]
+    pub spdi: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "Lift variants over to another assembly with this chain file"
+    )]
+    pub liftover: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "Look up CLNSIG of variants without it in this indexed ClinVar VCF file"
+    )]
+    pub clinvar: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "Add frequencies of variants in this indexed gnomAD sites VCF file"
+    )]
+    pub gnomad: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "Look up IDs of variants without one in this indexed dbSNP VCF file"
+    )]
+    pub dbsnp: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "Send the value of this INFO tag in scores, e.g. CADD_PHRED or REVEL (repeatable)"
+    )]
+    pub score: Vec<String>,
+    #[clap(
+        long,
+        help = "Send extra payload fields declared in this TOML file from INFO or FORMAT tags"
+    )]
+    pub mapping: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "Pass each variant to the transform function of this Rhai script, which can change it or return () to leave it out"
+    )]
+    pub script: Option<PathBuf>,
+    #[clap(
+        long,
+        arg_enum,
+        help = "Rename contigs to UCSC (chr1, chrM) or Ensembl (1, MT) style"
+    )]
+    pub contig_style: Option<ContigStyle>,
+    #[clap(
+        long,
+        help = "Rename contigs listed in file, with VCF and dataset names per line"
+    )]
+    pub rename_contigs: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "Import every ALT allele of a record as a separate variant"
+    )]
+    pub split_multiallelics: bool,
+    #[clap(long, help = "Skip structural variants (SVTYPE or symbolic ALT)")]
+    pub skip_svs: bool,
+    #[clap(long, help = "Skip breakend (BND) records")]
+    pub skip_bnd: bool,
+    #[clap(
+        long,
+        help = "Skip variants genotyped in less than this fraction of samples"
+    )]
+    pub min_call_rate: Option<f64>,
+    #[clap(
+        long,
+        help = "Withhold ALT alleles observed less than this number of times"
+    )]
+    pub min_allele_count: Option<u32>,
+    #[clap(
+        long,
+        help = "Withhold ALT alleles carried by less than this number of samples"
+    )]
+    pub min_carriers: Option<u32>,
+    #[clap(
+        long,
+        arg_enum,
+        help = "Round allele frequencies, or add noise to them, and leave out low counts"
+    )]
+    pub privacy_mode: Option<PrivacyMode>,
+    #[clap(
+        long,
+        default_value = "2",
+        help = "Round allele frequencies to this number of decimals in privacy mode"
+    )]
+    pub af_decimals: i32,
+    #[clap(
+        long,
+        default_value = "1.0",
+        help = "Privacy budget of the noise added to allele frequencies, lower is noisier"
+    )]
+    pub epsilon: f64,
+    #[clap(
+        long,
+        default_value = "10",
+        help = "Leave out allele, genotype and sample counts below this in privacy mode"
+    )]
+    pub min_reported_count: u32,
+    #[clap(
+        long,
+        default_value = "DP",
+        help = "FORMAT tag of the coverage distribution, e.g. DPI or NR"
+    )]
+    pub coverage_tag: String,
+    #[clap(
+        long,
+        default_value = "GQ",
+        help = "FORMAT tag of the genotype quality distribution"
+    )]
+    pub gq_tag: String,
+    #[clap(
+        long,
+        help = "Also send the distribution of this numeric FORMAT tag (repeatable)"
+    )]
+    pub distribution: Vec<String>,
+    #[clap(
+        long,
+        arg_enum,
+        default_value = "all",
+        help = "Send the ANN entry of the canonical, MANE or worst transcript of each allele, or all"
+    )]
+    pub transcript_policy: TranscriptPolicy,
+    #[clap(
+        long,
+        help = "Canonical transcripts, one per line, or MANE summary file for --transcript-policy"
+    )]
+    pub transcripts: Option<PathBuf>,
+    #[clap(
+        long,
+        arg_enum,
+        help = "Prefer ANN entries of RefSeq (NM_) or Ensembl (ENST) transcripts"
+    )]
+    pub transcript_source: Option<TranscriptSource>,
+    #[clap(
+        long,
+        arg_enum,
+        ignore_case = true,
+        help = "Skip variants without an ANN entry of at least this impact"
+    )]
+    pub min_impact: Option<Impact>,
+    #[clap(
+        long,
+        use_value_delimiter = true,
+        help = "Skip variants without one of these ANN consequences, e.g. missense_variant,stop_gained"
+    )]
+    pub consequences: Vec<String>,
+    #[clap(
+        long,
+        parse(try_from_str = parse_aggregation),
+        help = "Use all, first or sum of the values of each sample for a FORMAT tag, e.g. AD=sum"
+    )]
+    pub aggregate: Vec<(String, Aggregation)>,
+    #[clap(
+        long,
+        use_value_delimiter = true,
+        parse(try_from_str = parse_percentile),
+        help = "Add these percentiles to FORMAT distributions, e.g. 5,10,90,95"
+    )]
+    pub percentiles: Vec<usize>,
+    #[clap(
+        long,
+        use_value_delimiter = true,
+        help = "Add a histogram of coverage with these increasing bin edges, e.g. 0,10,20,30"
+    )]
+    pub coverage_bins: Vec<f64>,
+    #[clap(
+        long,
+        use_value_delimiter = true,
+        help = "Add a histogram of genotype quality with these increasing bin edges"
+    )]
+    pub gq_bins: Vec<f64>,
+    #[clap(
+        long,
+        arg_enum,
+        default_value = "drop",
+        help = "Remove spanning deletion (*) alleles or keep them"
+    )]
+    pub star_alleles: StarAlleles,
+    #[clap(long, help = "Read VCF files listed in file, one path per line")]
+    pub file_list: Option<PathBuf>,
+    #[clap(
+        required_unless_present = "file-list",
+        help = "VCF files or URLs, imported one after the other, - for stdin"
+    )]
+    pub vcf_files: Vec<String>,
+}
+
+impl InputOpts {
+    pub fn merge(&mut self, config: &Config) {
+        self.dont_filter |= config.dont_filter;
+        if !self.lenient {
+            self.strict |= config.strict;
+        }
+        self.bed = self.bed.take().or_else(|| config.bed.clone());
+        self.genes = self.genes.take().or_else(|| config.genes.clone());
+    }
+
+    /// Paths given as arguments and in file list, expanding glob patterns.
+    pub fn vcf_files(&self) -> Vec<String> {
+        let mut patterns = self.vcf_files.clone();
+        if let Some(path) = &self.file_list {
+            let content = fs::read_to_string(path)
+                .unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e));
+            patterns.extend(
+                content
+                    .lines()
+                    .map(|line| line.trim())
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(|line| line.to_string()),
+            );
+        }
+
+        let mut files = Vec::new();
+        for pattern in patterns {
+            if remote_url(&pattern).is_some() || !pattern.contains(['*', '?', '[']) {
+                files.push(pattern);
+                continue;
+            }
+            let mut matches: Vec<String> = glob(&pattern)
+                .unwrap_or_else(|e| panic!("Invalid pattern {}: {}", pattern, e))
+                .filter_map(|x| x.ok())
+                .map(|x| x.to_string_lossy().to_string())
+                .collect();
+            if matches.is_empty() {
+                warn!("No file matches {}", pattern);
+            }
+            matches.sort();
+            files.append(&mut matches);
+        }
+        files
+    }
+
+    pub fn regions(&self) -> Vec<Region> {
+        let mut regions = self.region.clone();
+        if let Some(path) = &self.regions_file {
+            let file = read_regions_file(path)
+                .unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e));
+            regions.extend(file);
+        }
+        regions
+    }
+
+    pub fn panel(&self) -> Option<RegionSet> {
+        let path = self.bed.as_ref()?;
+        let regions =
+            read_bed_file(path).unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e));
+        Some(RegionSet::new(regions))
+    }
+
+    pub fn contig_names(&self) -> ContigNames {
+        let map = match &self.rename_contigs {
+            Some(path) => {
+                read_map(path).unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e))
+            }
+            None => HashMap::new(),
+        };
+        ContigNames::new(self.contig_style, map)
+    }
+
+    pub fn reference(&self) -> Option<Fasta> {
+        let path = self.reference.as_ref()?;
+        let fasta = Fasta::open(path).unwrap_or_else(|e| panic!("Error opening {:?}: {}", path, e));
+        Some(fasta)
+    }
+
+    pub fn annotation_file(&self, path: &Option<PathBuf>) -> Option<AnnotationFile> {
+        let path = path.as_ref()?;
+        let file = AnnotationFile::open(path)
+            .unwrap_or_else(|e| panic!("Error opening {:?}: {}", path, e));
+        Some(file)
+    }
+
+    pub fn liftover(&self) -> Option<Chain> {
+        let path = self.liftover.as_ref()?;
+        let chain = Chain::load(path).unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e));
+        Some(chain)
+    }
+
+    pub fn mapping(&self) -> Mapping {
+        self.mapping
+            .as_deref()
+            .map(Mapping::load)
+            .unwrap_or_default()
+    }
+
+    pub fn privacy(&self) -> Option<Privacy> {
+        let mode = self.privacy_mode?;
+        assert!(self.epsilon > 0.0, "--epsilon must be positive");
+        Some(Privacy {
+            mode,
+            decimals: self.af_decimals,
+            epsilon: self.epsilon,
+            min_count: self.min_reported_count,
+        })
+    }
+
+    pub fn consequence_filter(&self) -> Option<ConsequenceFilter> {
+        if self.min_impact.is_none() && self.consequences.is_empty() {
+            return None;
+        }
+        Some(ConsequenceFilter {
+            min_impact: self.min_impact,
+            consequences: self.consequences.iter().cloned().collect(),
+        })
+    }
+
+    pub fn transcripts(&self) -> Transcripts {
+        let policy = self.transcript_policy;
+        if !matches!(policy, TranscriptPolicy::Canonical | TranscriptPolicy::Mane) {
+            return Transcripts::new();
+        }
+        let path = self
+            .transcripts
+            .as_ref()
+            .expect("--transcripts is required by this transcript policy");
+        read_transcripts(path, policy).unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e))
+    }
+
+    pub fn pseudonyms(&self) -> Pseudonyms {
+        match (&self.pseudonym_salt, &self.pseudonym_map) {
+            (Some(salt), _) => Pseudonyms::Salted(salt.clone()),
+            (_, Some(path)) => Pseudonyms::load_map(path),
+            _ => Pseudonyms::Raw,
+        }
+    }
+
+    pub fn sample_filter(&self) -> Option<SampleFilter> {
+        let mut include: Option<HashSet<String>> =
+            (!self.samples.is_empty()).then(|| self.samples.iter().cloned().collect());
+        if let Some(path) = &self.samples_file {
+            let content = fs::read_to_string(path)
+                .unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e));
+            let samples = content
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(|line| line.to_string());
+            include.get_or_insert_with(HashSet::new).extend(samples);
+        }
+        if include.is_none() && self.exclude_samples.is_empty() {
+            return None;
+        }
+        Some(SampleFilter {
+            include,
+            exclude: self.exclude_samples.iter().cloned().collect(),
+        })
+    }
+
+    pub fn genes(&self) -> Option<HashSet<String>> {
+        let path = self.genes.as_ref()?;
+        let content =
+            fs::read_to_string(path).unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e));
+        let genes = content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| line.to_string())
+            .collect();
+        Some(genes)
+    }
+}
+
+#[derive(Args)]
+pub struct UploadOpts {
+    #[clap(
+        long,
+        default_value = "1",
+        help = "Number of variants sent per request"
+    )]
+    pub batch_size: usize,
+    #[clap(
+        long,
+        default_value = "1",
+        help = "Number of concurrent upload requests"
+    )]
+    pub workers: usize,
+    #[clap(
+        long,
+        default_value = "3",
+        help = "Number of times a failed request is retried"
+    )]
+    pub retries: u32,
+    #[clap(
+        long,
+        default_value = "1.0",
+        help = "Seconds to wait before the first retry, doubled on every attempt"
+    )]
+    pub retry_backoff: f64,
+    #[clap(long, help = "Send at most this number of requests per second")]
+    pub max_requests_per_second: Option<f64>,
+    #[clap(
+        long,
+        arg_enum,
+        default_value = "create",
+        help = "Create new variants, or upsert to replace variants already on the server"
+    )]
+    pub mode: Mode,
+    #[clap(long, help = "Compress request bodies with gzip")]
+    pub compress_requests: bool,
+    #[clap(
+        long,
+        arg_enum,
+        default_value = "json",
+        help = "Encode variants as JSON, MessagePack or CBOR, falling back to JSON if the server does not accept it"
+    )]
+    pub wire_format: WireFormat,
+    #[clap(long, help = "Save position of the last uploaded variant to this file")]
+    pub checkpoint: Option<PathBuf>,
+    #[clap(
+        long,
+        requires = "checkpoint",
+        help = "Skip variants already uploaded according to the checkpoint file"
+    )]
+    pub resume: bool,
+    #[clap(
+        long,
+        help = "Record the id and payload digest of uploaded variants in this SQLite file"
+    )]
+    pub ledger: Option<PathBuf>,
+    #[clap(
+        long,
+        requires = "ledger",
+        help = "Skip variants the ledger has with the same payload"
+    )]
+    pub skip_already_imported: bool,
+    #[clap(
+        long,
+        use_value_delimiter = true,
+        requires = "kafka-topic",
+        help = "Publish variants to these Kafka brokers, e.g. kafka1:9092,kafka2:9092, instead of the server"
+    )]
+    pub kafka_brokers: Vec<String>,
+    #[clap(
+        long,
+        requires = "kafka-brokers",
+        help = "Kafka topic to publish variants to"
+    )]
+    pub kafka_topic: Option<String>,
+    #[clap(
+        long,
+        arg_enum,
+        default_value = "id",
+        help = "Key of the Kafka messages: the variant id, its contig and position, or none"
+    )]
+    pub kafka_key: MessageKey,
+    #[clap(
+        long,
+        arg_enum,
+        default_value = "all",
+        help = "Wait for all in-sync replicas, the leader or no broker to acknowledge messages"
+    )]
+    pub kafka_acks: Acks,
+    #[clap(
+        long,
+        conflicts_with = "kafka-topic",
+        help = "Index variants with the bulk API of this Elasticsearch server instead of the server"
+    )]
+    pub elasticsearch: Option<String>,
+    #[clap(
+        long,
+        default_value = "variants-{dataset}-{assembly}",
+        help = "Elasticsearch index, with {dataset} and {assembly} replaced"
+    )]
+    pub elasticsearch_index: String,
+    #[clap(
+        long,
+        env = "ELASTICSEARCH_API_KEY",
+        hide_env_values = true,
+        help = "Elasticsearch API key"
+    )]
+    pub elasticsearch_api_key: Option<String>,
+    #[clap(
+        long,
+        conflicts_with_all = &["kafka-topic", "elasticsearch"],
+        help = "Insert variants directly into the database of the server at this URI, e.g. mongodb://localhost/brave"
+    )]
+    pub backend: Option<String>,
+    #[clap(
+        long,
+        default_value = "variant",
+        help = "MongoDB collection of the variants"
+    )]
+    pub collection: String,
+}
+
+impl UploadOpts {
+    /// Position of the last uploaded variant when resuming an import.
+    pub fn resume_position(&self) -> Option<Position> {
+        let position = match &self.checkpoint {
+            Some(path) if self.resume => Checkpoint::load(path)
+                .unwrap_or_else(|e| panic!("Error reading checkpoint {:?}: {}", path, e)),
+            _ => None,
+        }?;
+        info!(
+            "Resuming after {}:{} (record {})",
+            position.reference_name, position.start, position.record
+        );
+        Some(position)
+    }
+
+    /// Rate limiter to share between the worker pools of an import.
+    pub fn rate_limiter(&self) -> RateLimiter {
+        assert!(
+            self.max_requests_per_second.map_or(true, |rate| rate > 0.0),
+            "--max-requests-per-second must be positive"
+        );
+        RateLimiter::new(self.max_requests_per_second)
+    }
+
+    /// Whether variants are sent to the server rather than to Kafka,
+    /// Elasticsearch or its database.
+    pub fn uses_server(&self) -> bool {
+        self.kafka_topic.is_none() && self.elasticsearch.is_none() && self.backend.is_none()
+    }
+
+    /// Key of the Kafka message of a variant, if publishing to Kafka.
+    pub fn message_key(&self, id: Option<&str>, position: &Position) -> Option<String> {
+        self.kafka_topic.as_ref()?;
+        self.kafka_key.key(id, position)
+    }
+
+    pub fn start(
+        &self,
+        server: &ServerOpts,
+        limiter: RateLimiter,
+        progress: Progress,
+    ) -> WorkerPool {
+        let retry = RetryPolicy {
+            retries: self.retries,
+            backoff: Duration::from_secs_f64(self.retry_backoff),
+        };
+        let sink = match (&self.kafka_topic, &self.elasticsearch, &self.backend) {
+            (Some(topic), _, _) => {
+                let kafka = Kafka::new(
+                    &self.kafka_brokers,
+                    topic,
+                    self.kafka_acks,
+                    retry,
+                    progress.clone(),
+                )
+                .unwrap_or_else(|e| {
+                    Exit::Unreachable.fail(format!("Error connecting to Kafka: {}", e))
+                });
+                Sink::Kafka(kafka)
+            }
+            (_, Some(url), _) => Sink::Elasticsearch(Elasticsearch::new(
+                server.transport_client(),
+                url,
+                &self.elasticsearch_index,
+                self.elasticsearch_api_key.clone(),
+                self.wire_format,
+                retry,
+                progress.clone(),
+            )),
+            (_, _, Some(uri)) => {
+                assert!(
+                    uri.starts_with("mongodb://") || uri.starts_with("mongodb+srv://"),
+                    "Unsupported backend {}, only MongoDB is supported",
+                    uri
+                );
+                let mongodb = MongoDb::new(
+                    uri,
+                    &self.collection,
+                    self.mode,
+                    self.wire_format,
+                    progress.clone(),
+                )
+                .unwrap_or_else(|e| {
+                    Exit::Unreachable.fail(format!("Error connecting to MongoDB: {}", e))
+                });
+                Sink::MongoDb(mongodb)
+            }
+            (None, None, None) => {
+                let uploader = Uploader::new(
+                    server.client(),
+                    server.host(),
+                    server.auth(),
+                    retry,
+                    limiter,
+                    self.compress_requests,
+                    progress.clone(),
+                )
+                .with_mode(self.mode)
+                .with_format(self.wire_format);
+                if server.api_version.is_some_and(|v| !v.supports_batch()) {
+                    uploader.disable_batch();
+                }
+                Sink::Server(uploader)
+            }
+        };
+        let checkpoint = self.checkpoint.clone().map(Checkpoint::new);
+        let ledger = self.ledger.as_ref().map(|path| {
+            Ledger::open(path).unwrap_or_else(|e| panic!("Error opening {:?}: {}", path, e))
+        });
+        WorkerPool::new(
+            sink,
+            progress,
+            self.workers,
+            self.batch_size,
+            checkpoint,
+            ledger,
+            self.skip_already_imported,
+        )
+    }
+}
+
+#[derive(Args)]
+pub struct ImportOpts {
+    #[clap(flatten)]
+    pub server: ServerOpts,
+    #[clap(flatten)]
+    pub dataset: DatasetOpts,
+    #[clap(flatten)]
+    pub input: InputOpts,
+    #[clap(long, help = "Just check VCF without connecting to server")]
+    pub dryrun: bool,
+    #[clap(
+        long,
+        default_value = "-",
+        help = "File to write JSON validation report to on dry runs, - for stdout"
+    )]
+    pub report: PathBuf,
+    #[clap(
+        long,
+        default_value = "10",
+        help = "Number of offending records listed in validation report"
+    )]
+    pub report_examples: usize,
+    #[clap(long, help = "Create dataset if it does not exist on the server")]
+    pub create_dataset: bool,
+    #[clap(
+        long,
+        requires = "create-dataset",
+        help = "Description of created dataset"
+    )]
+    pub dataset_description: Option<String>,
+    #[clap(
+        long,
+        help = "Also write imported variants as JSON lines to this file, or to a DuckDB table if it ends with .duckdb"
+    )]
+    pub output: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "Write a JSON summary of the import to this file, e.g. for workflow engines"
+    )]
+    pub summary_file: Option<PathBuf>,
+    #[clap(long, help = "Print variant data to stderr")]
+    pub debug: bool,
+    #[clap(long, help = "Don't show progress bar")]
+    pub no_progress: bool,
+    #[clap(
+        long,
+        default_value = "1",
+        conflicts_with_all = &["checkpoint", "htsget"],
+        help = "Number of contigs imported in parallel, requires indexed files"
+    )]
+    pub parallel_contigs: usize,
+    #[clap(flatten)]
+    pub upload: UploadOpts,
+}
+
+#[derive(Args)]
+pub struct ValidateOpts {
+    #[clap(flatten)]
+    pub input: InputOpts,
+}
+
+#[derive(Args)]
+pub struct ExportOpts {
+    #[clap(flatten)]
+    pub dataset: DatasetOpts,
+    #[clap(flatten)]
+    pub input: InputOpts,
+    #[clap(
+        short,
+        long,
+        default_value = "-",
+        help = "File to write variants to, - for stdout"
+    )]
+    pub output: PathBuf,
+    #[clap(
+        long,
+        arg_enum,
+        default_value = "ndjson",
+        help = "Write variants as JSON lines, a Parquet file, an Arrow IPC stream, a CSV or TSV table with one row per ALT allele, or a DuckDB table, always used for .duckdb files"
+    )]
+    pub output_format: ExportFormat,
+    #[clap(
+        long,
+        help = "Also write the BigQuery table schema of the variants to this file"
+    )]
+    pub bigquery_schema: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct UploadNdjsonOpts {
+    #[clap(flatten)]
+    pub server: ServerOpts,
+    #[clap(flatten)]
+    pub upload: UploadOpts,
+    #[clap(help = "File with one variant as JSON per line, - for stdin")]
+    pub ndjson_file: PathBuf,
+}
+
+#[derive(Args)]
+pub struct DeleteOpts {
+    #[clap(flatten)]
+    pub server: ServerOpts,
+    #[clap(long, help = "Dataset name")]
+    pub dataset: String,
+    #[clap(long, help = "Only delete variants of this genome assembly version")]
+    pub assembly: Option<String>,
+    #[clap(long, help = "Only delete variants in region chr:start-end")]
+    pub region: Option<Region>,
+}
+
+#[derive(Args)]
+pub struct DatasetMetadataOpts {
+    #[clap(flatten)]
+    pub server: ServerOpts,
+    #[clap(help = "TOML file with the dataset id, assembly and other metadata")]
+    pub metadata_file: PathBuf,
+    #[clap(
+        long,
+        help = "Add cohort composition from a directory of phenopacket JSON files or a cohort JSON file"
+    )]
+    pub phenopackets: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct WatchOpts {
+    #[clap(long, help = "Directory to watch for new VCF files")]
+    pub dir: PathBuf,
+    #[clap(
+        long,
+        default_value = "*.vcf.gz",
+        help = "Import files whose name matches this pattern"
+    )]
+    pub pattern: String,
+    #[clap(
+        long,
+        help = "File listing the name, dataset and assembly of files, instead of names like dataset.assembly.vcf.gz"
+    )]
+    pub manifest: Option<PathBuf>,
+    #[clap(long, help = "Move imported files here [default: DIR/done]")]
+    pub done_dir: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "Move files that failed to import here [default: DIR/failed]"
+    )]
+    pub failed_dir: Option<PathBuf>,
+    #[clap(
+        long,
+        default_value = "10",
+        help = "Seconds between scans of the directory"
+    )]
+    pub interval: u64,
+    #[clap(
+        last = true,
+        help = "Options of import, e.g. -- --host https://brave.example.org"
+    )]
+    pub import_args: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct ServeOpts {
+    #[clap(long, default_value = "127.0.0.1:8000", help = "Address to listen on")]
+    pub listen: String,
+    #[clap(
+        long,
+        default_value = "spool",
+        help = "Directory keeping uploaded files until they are imported"
+    )]
+    pub spool_dir: PathBuf,
+    #[clap(
+        long,
+        env = "BRAVE_SERVE_TOKEN",
+        hide_env_values = true,
+        help = "Require this bearer token from clients"
+    )]
+    pub token: Option<String>,
+    #[clap(
+        long,
+        default_value = "10240",
+        help = "Largest upload accepted, in MiB"
+    )]
+    pub max_upload_size: u64,
+    #[clap(
+        last = true,
+        help = "Options of import, e.g. -- --host https://brave.example.org"
+    )]
+    pub import_args: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct StatsOpts {
+    pub vcf_file: String,
+}
+
+#[derive(Args)]
+pub struct LoginOpts {
+    #[clap(flatten)]
+    pub server: ServerOpts,
+}
+
+#[derive(Args)]
+pub struct DiffOpts {
+    #[clap(flatten)]
+    pub server: ServerOpts,
+    #[clap(flatten)]
+    pub dataset: DatasetOpts,
+    #[clap(flatten)]
+    pub input: InputOpts,
+}
+
+#[derive(ArgEnum, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Json,
+    Tsv,
+}
+
+#[derive(Args)]
+pub struct QueryOpts {
+    #[clap(flatten)]
+    pub server: ServerOpts,
+    #[clap(long, help = "Dataset name")]
+    pub dataset: String,
+    #[clap(long, help = "Only fetch variants of this genome assembly version")]
+    pub assembly: Option<String>,
+    #[clap(long, help = "Only fetch variants in region chr:start-end")]
+    pub region: Option<Region>,
+    #[clap(
+        long,
+        arg_enum,
+        default_value = "json",
+        help = "Print variants as JSON lines or as a table"
+    )]
+    pub format: OutputFormat,
+    #[clap(
+        short,
+        long,
+        default_value = "-",
+        help = "File to write variants to, - for stdout"
+    )]
+    pub output: PathBuf,
+}
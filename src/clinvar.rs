@@ -0,0 +1,89 @@
+use crate::annotation::AnnotationFile;
+use crate::error::Result;
+use crate::variant::{get_info_field, has_info, Variant};
+
+/// Clinical significance terms of ClinVar, as displayed on its website.
+const TERMS: [&str; 15] = [
+    "Benign",
+    "Likely benign",
+    "Uncertain significance",
+    "Likely pathogenic",
+    "Pathogenic",
+    "Conflicting interpretations of pathogenicity",
+    "Conflicting classifications of pathogenicity",
+    "drug response",
+    "association",
+    "risk factor",
+    "protective",
+    "Affects",
+    "confers sensitivity",
+    "not provided",
+    "other",
+];
+
+/// Spell a term like ClinVar's controlled vocabulary, keeping a
+/// submission count such as `(3)`.
+fn canonical_term(term: &str) -> String {
+    let (name, count) = match term.find('(') {
+        Some(i) => term.split_at(i),
+        None => (term, ""),
+    };
+    let name = name.trim();
+    let name = TERMS
+        .iter()
+        .find(|x| x.eq_ignore_ascii_case(name))
+        .map_or(name, |x| x);
+    format!("{}{}", name, count)
+}
+
+/// Turn the values of CLNSIG or CLNSIGCONF, such as
+/// `Pathogenic/Likely_pathogenic|_risk_factor`, into readable text:
+/// `Pathogenic/Likely pathogenic, risk factor`.
+pub fn normalize_significance(values: &[String]) -> String {
+    values
+        .join("|")
+        .replace('_', " ")
+        .split(['|', ','])
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(|assertion| {
+            assertion
+                .split('/')
+                .map(canonical_term)
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Turn the values of CLNREVSTAT, split on commas by htslib, back into the
+/// review status, e.g. `criteria provided, multiple submitters, no conflicts`.
+pub fn normalize_review_status(values: &[String]) -> String {
+    values.join(",").replace('_', " ")
+}
+
+/// Fill the ClinVar fields of a variant that has no CLNSIG from the record
+/// of the same variant in a ClinVar VCF file, whose ID is the variation ID.
+pub fn annotate(clinvar: &mut AnnotationFile, v: &mut Variant) -> Result<()> {
+    if v.clnsig.is_some() {
+        return Ok(());
+    }
+    let record = match clinvar.find(v)? {
+        Some((record, _)) => record,
+        None => return Ok(()),
+    };
+    let info = |tag: &str| -> Result<Option<Vec<String>>> {
+        if has_info(&record, tag) {
+            get_info_field(&record, tag)
+        } else {
+            Ok(None)
+        }
+    };
+    v.clnsig = info("CLNSIG")?.map(|x| normalize_significance(&x));
+    v.clnsigconf = info("CLNSIGCONF")?.map(|x| normalize_significance(&x));
+    v.clnrevstat = info("CLNREVSTAT")?.map(|x| normalize_review_status(&x));
+    let id = String::from_utf8_lossy(&record.id()).to_string();
+    v.clinvar_id = (id != ".").then_some(id);
+    Ok(())
+}
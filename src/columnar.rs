@@ -0,0 +1,140 @@
+use crate::schema::{resolve, types};
+use crate::variant::Variant;
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::error::ArrowError;
+use arrow::json::reader::{Decoder, ReaderBuilder};
+use arrow::record_batch::RecordBatch;
+use schemars::schema_for;
+use serde_json::Value;
+use std::slice;
+use std::sync::Arc;
+
+/// Number of variants per record batch.
+const BATCH_SIZE: usize = 8192;
+
+/// Arrow schema of the variants written by export, derived from their JSON
+/// Schema like the BigQuery one. Arrays become lists, objects structs, and
+/// objects with arbitrary keys, such as scores, maps. Fields added with
+/// --mapping are left out.
+pub fn schema() -> SchemaRef {
+    let root = serde_json::to_value(schema_for!(Variant)).unwrap();
+    let definitions = root.get("definitions").cloned().unwrap_or_default();
+    Arc::new(Schema::new(fields(&root, &definitions)))
+}
+
+fn fields(schema: &Value, definitions: &Value) -> Vec<Field> {
+    schema["properties"]
+        .as_object()
+        .map(|properties| {
+            properties
+                .iter()
+                .map(|(name, property)| Field::new(name, data_type(property, definitions), true))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn data_type(schema: &Value, definitions: &Value) -> DataType {
+    let (schema, _) = resolve(schema, definitions);
+    let kinds = types(&schema);
+    if kinds.contains(&"array") {
+        let item = data_type(&schema["items"], definitions);
+        DataType::List(Arc::new(Field::new("item", item, true)))
+    } else if kinds.contains(&"object") {
+        if schema.get("properties").is_some() {
+            DataType::Struct(fields(&schema, definitions).into())
+        } else {
+            let value = data_type(&schema["additionalProperties"], definitions);
+            let entries = vec![
+                Field::new("keys", DataType::Utf8, false),
+                Field::new("values", value, true),
+            ];
+            let entries = Field::new("entries", DataType::Struct(entries.into()), false);
+            DataType::Map(Arc::new(entries), false)
+        }
+    } else if kinds.contains(&"integer") {
+        DataType::Int64
+    } else if kinds.contains(&"number") {
+        DataType::Float64
+    } else if kinds.contains(&"boolean") {
+        DataType::Boolean
+    } else {
+        DataType::Utf8
+    }
+}
+
+/// Variants gathered into Arrow record batches.
+pub struct Batches {
+    decoder: Decoder,
+    rows: usize,
+}
+
+impl Batches {
+    pub fn new(schema: SchemaRef) -> Self {
+        let decoder = ReaderBuilder::new(schema)
+            .with_batch_size(BATCH_SIZE)
+            .build_decoder()
+            .unwrap();
+        Batches { decoder, rows: 0 }
+    }
+
+    /// Add a variant, returning a batch once it is full.
+    pub fn push(&mut self, v: &Variant) -> Result<Option<RecordBatch>, ArrowError> {
+        self.decoder.serialize(slice::from_ref(v))?;
+        self.rows += 1;
+        if self.rows < BATCH_SIZE {
+            return Ok(None);
+        }
+        self.flush()
+    }
+
+    /// Batch of the variants added since the last one, if any.
+    pub fn flush(&mut self) -> Result<Option<RecordBatch>, ArrowError> {
+        self.rows = 0;
+        self.decoder.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn schema_follows_json_schema() {
+        let schema = schema();
+        let data_type = |name: &str| schema.field_with_name(name).unwrap().data_type().clone();
+        assert_eq!(data_type("referenceName"), DataType::Utf8);
+        assert_eq!(data_type("start"), DataType::Int64);
+        assert_eq!(data_type("totalSamples"), DataType::Int64);
+        assert!(matches!(
+            data_type("alleleFrequency"),
+            DataType::List(item) if item.data_type() == &DataType::Float64
+        ));
+        assert!(matches!(data_type("coverage"), DataType::Struct(_)));
+        assert!(matches!(data_type("scores"), DataType::Map(..)));
+        assert!(schema.fields().iter().all(|field| field.name() != "mapped"));
+    }
+
+    #[test]
+    fn variants_are_gathered_into_batches() {
+        let v: Variant = serde_json::from_value(json!({
+            "datasetId": "test",
+            "assemblyId": "hg38",
+            "totalSamples": 2,
+            "referenceName": "1",
+            "start": 100,
+            "referenceBases": "A",
+            "alternateBases": ["G"],
+            "alleleFrequency": [0.25],
+            "scores": {"CADD_PHRED": 23.1},
+        }))
+        .unwrap();
+        let mut batches = Batches::new(schema());
+        assert!(batches.push(&v).unwrap().is_none());
+        assert!(batches.push(&v).unwrap().is_none());
+        let batch = batches.flush().unwrap().unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        assert!(batches.flush().unwrap().is_none());
+    }
+}
@@ -0,0 +1,60 @@
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Defaults read from a TOML file, overridden by command line options.
+/// Keys are named like the options, e.g. `host = "https://brave.example.org"`
+/// or `dont-filter = true`.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    pub host: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub token: Option<String>,
+    pub token_file: Option<PathBuf>,
+    pub api_key_header: Option<String>,
+    #[serde(default)]
+    pub header: Vec<String>,
+    #[serde(default)]
+    pub disable_ssl: bool,
+    pub ca_cert: Option<PathBuf>,
+    pub proxy: Option<String>,
+    pub proxy_username: Option<String>,
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+    #[serde(default)]
+    pub dont_filter: bool,
+    #[serde(default)]
+    pub strict: bool,
+    pub bed: Option<PathBuf>,
+    pub genes: Option<PathBuf>,
+}
+
+impl Config {
+    /// Read the file given with `--config`, or the default configuration file
+    /// if it exists.
+    pub fn load(path: Option<&Path>) -> Self {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_path().filter(|path| path.exists()) {
+                Some(path) => path,
+                None => return Config::default(),
+            },
+        };
+        let content =
+            fs::read_to_string(&path).unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e));
+        toml::from_str(&content).unwrap_or_else(|e| panic!("Invalid config {:?}: {}", path, e))
+    }
+}
+
+/// `$XDG_CONFIG_HOME/brave-import/config.toml`, where XDG_CONFIG_HOME
+/// defaults to `~/.config`.
+fn default_path() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("brave-import").join("config.toml"))
+}
@@ -0,0 +1,39 @@
+use crate::variant::AnnFields;
+use clap::ArgEnum;
+use std::collections::HashSet;
+
+/// SnpEff putative impact of an ANN entry, from least to most severe.
+#[derive(ArgEnum, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Impact {
+    Modifier,
+    Low,
+    Moderate,
+    High,
+}
+
+impl Impact {
+    /// Impact of the Annotation_Impact field, MODIFIER if unknown.
+    pub fn parse(impact: &str) -> Self {
+        Impact::from_str(impact, true).unwrap_or(Impact::Modifier)
+    }
+}
+
+/// Keeps variants with an ANN entry of at least some impact or with one of
+/// some consequences (Sequence Ontology terms such as `stop_gained`).
+pub struct ConsequenceFilter {
+    pub min_impact: Option<Impact>,
+    pub consequences: HashSet<String>,
+}
+
+impl ConsequenceFilter {
+    pub fn matches(&self, entries: &[Vec<String>], ann: &AnnFields) -> bool {
+        entries.iter().any(|entry| {
+            let field = |i: usize| entry.get(i).map_or("", String::as_str);
+            self.min_impact
+                .is_some_and(|min| Impact::parse(field(ann.impact)) >= min)
+                || field(ann.consequence)
+                    .split('&')
+                    .any(|x| self.consequences.contains(x))
+        })
+    }
+}
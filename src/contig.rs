@@ -0,0 +1,64 @@
+use clap::ArgEnum;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Naming convention of contigs: `chr1` and `chrM`, or `1` and `MT`.
+#[derive(ArgEnum, Clone, Copy, PartialEq)]
+pub enum ContigStyle {
+    Ucsc,
+    Ensembl,
+}
+
+/// Renaming of contigs from the VCF file to the names expected by the
+/// dataset. Names listed in the map take precedence over the style.
+pub struct ContigNames {
+    style: Option<ContigStyle>,
+    map: HashMap<String, String>,
+}
+
+impl ContigNames {
+    pub fn new(style: Option<ContigStyle>, map: HashMap<String, String>) -> Self {
+        ContigNames { style, map }
+    }
+
+    pub fn rename(&self, name: &str) -> String {
+        if let Some(renamed) = self.map.get(name) {
+            return renamed.clone();
+        }
+        match self.style {
+            None => name.to_string(),
+            Some(ContigStyle::Ucsc) => match name {
+                "MT" | "M" => "chrM".to_string(),
+                name if name.starts_with("chr") => name.to_string(),
+                name => format!("chr{}", name),
+            },
+            Some(ContigStyle::Ensembl) => match name {
+                "chrM" | "chrMT" => "MT".to_string(),
+                name => name.strip_prefix("chr").unwrap_or(name).to_string(),
+            },
+        }
+    }
+}
+
+/// Read a file with two whitespace-separated columns, the contig name in
+/// the VCF file and the name to send to the server.
+pub fn read_map(path: &Path) -> io::Result<HashMap<String, String>> {
+    let content = fs::read_to_string(path)?;
+    let mut map = HashMap::new();
+    for line in content.lines() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid contig mapping in {:?}: {}", path, line),
+            ));
+        }
+        map.insert(fields[0].to_string(), fields[1].to_string());
+    }
+    Ok(map)
+}
@@ -0,0 +1,149 @@
+use crate::cli::{DatasetMetadataOpts, DatasetOpts, ServerOpts};
+use crate::exit::Exit;
+use crate::phenopacket::Cohort;
+use crate::upload::{rejected, request_error};
+use log::info;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Dataset<'a> {
+    id: &'a str,
+    assembly_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+}
+
+/// Make sure the dataset exists before importing into it, creating it if
+/// create is set, so a typo in `--dataset` is reported right away.
+pub fn ensure_exists(
+    server: &ServerOpts,
+    dataset: &DatasetOpts,
+    create: bool,
+    description: Option<&str>,
+) {
+    let client = server.client();
+    let url = format!("{}/datasets/{}", server.host(), dataset.dataset);
+
+    let res = server
+        .auth()
+        .apply(client.get(url))
+        .send()
+        .unwrap_or_else(|e| Exit::for_error(&e).fail(request_error(&e)));
+
+    match res.status() {
+        status if status.is_success() => return,
+        StatusCode::NOT_FOUND if create => {}
+        StatusCode::NOT_FOUND => Exit::UploadFailed.fail(format!(
+            "Dataset {} does not exist on {}, use --create-dataset to create it",
+            dataset.dataset,
+            server.host()
+        )),
+        status => Exit::for_status(status).fail(format!(
+            "Error checking dataset {}: {}",
+            dataset.dataset, status
+        )),
+    }
+
+    let body = Dataset {
+        id: &dataset.dataset,
+        assembly_id: &dataset.assembly,
+        description,
+    };
+    let res = server
+        .auth()
+        .apply(client.post(format!("{}/datasets", server.host())))
+        .json(&body)
+        .send()
+        .unwrap_or_else(|e| Exit::for_error(&e).fail(request_error(&e)));
+    if !res.status().is_success() {
+        rejected(res);
+    }
+
+    info!("Created dataset {}", dataset.dataset);
+}
+
+/// Dataset-level metadata read from a TOML file, with keys like
+/// `assembly = "hg38"` or `duo-codes = ["DUO:0000042"]`.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all(deserialize = "kebab-case", serialize = "camelCase"))]
+#[serde(deny_unknown_fields)]
+struct Metadata {
+    id: String,
+    #[serde(rename(serialize = "assemblyId"))]
+    assembly: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sample_count: Option<u32>,
+    /// Data use conditions as Data Use Ontology terms.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    duo_codes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contact_email: Option<String>,
+    /// Composition of the cohort, from --phenopackets.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    cohort: Option<Cohort>,
+}
+
+/// Create the dataset described by the metadata file, or update it if it
+/// already exists.
+pub fn run(opts: DatasetMetadataOpts) {
+    let path = &opts.metadata_file;
+    let content =
+        fs::read_to_string(path).unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e));
+    let mut metadata: Metadata = toml::from_str(&content)
+        .unwrap_or_else(|e| panic!("Invalid dataset metadata {:?}: {}", path, e));
+    for code in &metadata.duo_codes {
+        assert!(
+            code.strip_prefix("DUO:")
+                .is_some_and(|x| x.len() == 7 && x.bytes().all(|x| x.is_ascii_digit())),
+            "Invalid DUO code {}, expected e.g. DUO:0000042",
+            code
+        );
+    }
+    if let Some(email) = &metadata.contact_email {
+        assert!(email.contains('@'), "Invalid contact email {}", email);
+    }
+
+    if let Some(path) = &opts.phenopackets {
+        let cohort = Cohort::load(path);
+        info!("Read {} phenopackets from {:?}", cohort.individuals, path);
+        metadata.sample_count.get_or_insert(cohort.individuals);
+        metadata.cohort = Some(cohort);
+    }
+
+    let server = &opts.server;
+    let client = server.client();
+    let url = format!("{}/datasets/{}", server.host(), metadata.id);
+    let res = server
+        .auth()
+        .apply(client.get(&url))
+        .send()
+        .unwrap_or_else(|e| Exit::for_error(&e).fail(request_error(&e)));
+
+    let (req, action) = match res.status() {
+        status if status.is_success() => (client.put(&url), "Updated"),
+        StatusCode::NOT_FOUND => (
+            client.post(format!("{}/datasets", server.host())),
+            "Created",
+        ),
+        status => Exit::for_status(status).fail(format!(
+            "Error checking dataset {}: {}",
+            metadata.id, status
+        )),
+    };
+    let res = server
+        .auth()
+        .apply(req)
+        .json(&metadata)
+        .send()
+        .unwrap_or_else(|e| Exit::for_error(&e).fail(request_error(&e)));
+    if !res.status().is_success() {
+        rejected(res);
+    }
+
+    info!("{} dataset {}", action, metadata.id);
+}
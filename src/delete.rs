@@ -0,0 +1,35 @@
+use crate::cli::DeleteOpts;
+use crate::exit::Exit;
+use crate::remote::variant_query;
+use crate::upload::{rejected, request_error};
+use log::info;
+
+pub fn run(opts: DeleteOpts) {
+    let client = opts.server.client();
+
+    let query = variant_query(
+        &opts.dataset,
+        opts.assembly.as_deref(),
+        opts.region.as_ref(),
+    );
+
+    let res = opts
+        .server
+        .auth()
+        .apply(client.delete(format!("{}/variants", opts.server.host())))
+        .query(&query)
+        .send()
+        .unwrap_or_else(|e| Exit::for_error(&e).fail(request_error(&e)));
+    if !res.status().is_success() {
+        rejected(res);
+    }
+
+    let mut scope = format!("dataset {}", opts.dataset);
+    if let Some(assembly) = &opts.assembly {
+        scope += &format!(", assembly {}", assembly);
+    }
+    if let Some(region) = &opts.region {
+        scope += &format!(", region {}", region);
+    }
+    info!("Deleted variants of {}", scope);
+}
@@ -0,0 +1,126 @@
+use crate::cli::DiffOpts;
+use crate::htsget;
+use crate::reader::VariantReader;
+use crate::remote::{variant_query, VariantPages};
+use crate::variant::Variant;
+use log::info;
+use std::collections::HashMap;
+use std::process;
+
+/// Position, REF and ALT identifying a variant within a dataset.
+type Key = (String, i64, String, String);
+
+fn key(v: &Variant) -> Key {
+    (
+        v.reference_name.clone(),
+        v.start,
+        v.reference_bases.clone(),
+        v.alternate_bases.join(","),
+    )
+}
+
+/// Names of the fields whose values differ between the two variants.
+fn changed_fields(local: &Variant, remote: &Variant) -> Vec<&'static str> {
+    let same_af = local.allele_frequency.len() == remote.allele_frequency.len()
+        && local
+            .allele_frequency
+            .iter()
+            .zip(&remote.allele_frequency)
+            .all(|(a, b)| (a - b).abs() < 1e-6);
+
+    let mut fields = Vec::new();
+    if !same_af {
+        fields.push("alleleFrequency");
+    }
+    if local.sample_count != remote.sample_count {
+        fields.push("sampleCount");
+    }
+    if local.snp_ids != remote.snp_ids {
+        fields.push("snpIds");
+    }
+    if local.gene_symbol != remote.gene_symbol {
+        fields.push("geneSymbol");
+    }
+    if local.hgvs != remote.hgvs {
+        fields.push("hgvs");
+    }
+    if local.variant_type != remote.variant_type {
+        fields.push("type");
+    }
+    if local.clnsig != remote.clnsig {
+        fields.push("clnsig");
+    }
+    fields
+}
+
+/// Print one line per variant missing on the server, stale on the server or
+/// different from the VCF file, and exit with 1 if there is any.
+pub fn run(opts: DiffOpts) {
+    if opts.input.htsget {
+        htsget::set_client(opts.server.transport_client());
+    }
+    let regions = opts.input.regions();
+    let dataset = &opts.dataset.dataset;
+    let assembly = Some(opts.dataset.assembly.as_str());
+
+    let queries = if regions.is_empty() {
+        vec![variant_query(dataset, assembly, None)]
+    } else {
+        regions
+            .iter()
+            .map(|region| variant_query(dataset, assembly, Some(region)))
+            .collect()
+    };
+    let mut remote: HashMap<Key, Variant> = queries
+        .into_iter()
+        .flat_map(|query| VariantPages::new(&opts.server, query))
+        .map(|v| (key(&v), v))
+        .collect();
+    info!("Fetched {} variants from server", remote.len());
+
+    let (mut missing, mut changed) = (0, 0);
+    let reader = VariantReader::new(
+        &opts.input,
+        opts.dataset.dataset.clone(),
+        opts.dataset.assembly.clone(),
+    );
+    for (_, local) in reader {
+        let (contig, start, reference, alternate) = key(&local);
+        match remote.remove(&key(&local)) {
+            None => {
+                println!(
+                    "missing\t{}:{}\t{}\t{}",
+                    contig, start, reference, alternate
+                );
+                missing += 1;
+            }
+            Some(v) => {
+                let fields = changed_fields(&local, &v);
+                if !fields.is_empty() {
+                    println!(
+                        "changed\t{}:{}\t{}\t{}\t{}",
+                        contig,
+                        start,
+                        reference,
+                        alternate,
+                        fields.join(",")
+                    );
+                    changed += 1;
+                }
+            }
+        }
+    }
+
+    let mut stale: Vec<Key> = remote.into_keys().collect();
+    stale.sort();
+    for (contig, start, reference, alternate) in &stale {
+        println!("stale\t{}:{}\t{}\t{}", contig, start, reference, alternate);
+    }
+
+    info!("Missing on server: {}", missing);
+    info!("Stale on server: {}", stale.len());
+    info!("Changed: {}", changed);
+    if missing > 0 || changed > 0 || !stale.is_empty() {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::columnar::{self, Batches};
+use crate::variant::Variant;
+use arrow::datatypes::DataType;
+use arrow::error::ArrowError;
+use duckdb::{params, Connection, Error};
+use std::path::Path;
+
+const TABLE: &str = "variants";
+
+/// DuckDB database with a table of the variants, with the same columns as
+/// Parquet exports. Variants of the dataset and assembly already in the
+/// table are replaced, so the table mirrors the last import of each. The
+/// old variants are only deleted once every new one is written, all in one
+/// transaction committed by `finish`.
+pub struct DuckDb {
+    conn: Connection,
+    batches: Batches,
+}
+
+impl DuckDb {
+    pub fn open(path: &Path, dataset: &str, assembly: &str) -> duckdb::Result<Self> {
+        let conn = Connection::open(path)?;
+        let schema = columnar::schema();
+        let columns: Vec<_> = schema
+            .fields()
+            .iter()
+            .map(|field| format!("\"{}\" {}", field.name(), sql_type(field.data_type())))
+            .collect();
+        conn.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {} ({})",
+            TABLE,
+            columns.join(", ")
+        ))?;
+        conn.execute_batch("BEGIN TRANSACTION")?;
+        conn.execute(
+            &format!(
+                "DELETE FROM {} WHERE \"datasetId\" = ? AND \"assemblyId\" = ?",
+                TABLE
+            ),
+            params![dataset, assembly],
+        )?;
+        Ok(DuckDb {
+            conn,
+            batches: Batches::new(schema),
+        })
+    }
+
+    pub fn write(&mut self, v: &Variant) -> duckdb::Result<()> {
+        match self.batches.push(v).map_err(arrow_error)? {
+            Some(batch) => self.conn.appender(TABLE)?.append_record_batch(batch),
+            None => Ok(()),
+        }
+    }
+
+    pub fn finish(mut self) -> duckdb::Result<()> {
+        if let Some(batch) = self.batches.flush().map_err(arrow_error)? {
+            self.conn.appender(TABLE)?.append_record_batch(batch)?;
+        }
+        self.conn.execute_batch("COMMIT")
+    }
+}
+
+fn arrow_error(e: ArrowError) -> Error {
+    Error::ToSqlConversionFailure(Box::new(e))
+}
+
+fn sql_type(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Boolean => "BOOLEAN".to_string(),
+        DataType::Int64 => "BIGINT".to_string(),
+        DataType::Float64 => "DOUBLE".to_string(),
+        DataType::List(item) => format!("{}[]", sql_type(item.data_type())),
+        DataType::Struct(fields) => {
+            let fields: Vec<_> = fields
+                .iter()
+                .map(|field| format!("\"{}\" {}", field.name(), sql_type(field.data_type())))
+                .collect();
+            format!("STRUCT({})", fields.join(", "))
+        }
+        DataType::Map(entries, _) => match entries.data_type() {
+            DataType::Struct(fields) => {
+                format!("MAP(VARCHAR, {})", sql_type(fields[1].data_type()))
+            }
+            _ => unreachable!(),
+        },
+        _ => "VARCHAR".to_string(),
+    }
+}
@@ -0,0 +1,138 @@
+use crate::exit::Exit;
+use crate::progress::Progress;
+use crate::upload::{rejected, request_error, Payload, RetryPolicy, WireFormat};
+use log::warn;
+use reqwest::blocking::Client;
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::thread;
+
+/// Fields of a variant deciding its index and document id.
+#[derive(Deserialize)]
+struct Document {
+    id: Option<String>,
+    #[serde(rename = "datasetId")]
+    dataset_id: String,
+    #[serde(rename = "assemblyId")]
+    assembly_id: String,
+}
+
+#[derive(Deserialize)]
+struct BulkResponse {
+    errors: bool,
+    items: Vec<Value>,
+}
+
+/// Writer of variants to Elasticsearch indexes with the `_bulk` API. The
+/// index of a variant is the template with `{dataset}` and `{assembly}`
+/// replaced, and its document id the variant id, so that imports can be
+/// run again.
+pub struct Elasticsearch {
+    client: Client,
+    bulk_url: String,
+    index: String,
+    api_key: Option<String>,
+    format: WireFormat,
+    retry: RetryPolicy,
+    progress: Progress,
+}
+
+impl Elasticsearch {
+    pub fn new(
+        client: Client,
+        url: &str,
+        index: &str,
+        api_key: Option<String>,
+        format: WireFormat,
+        retry: RetryPolicy,
+        progress: Progress,
+    ) -> Self {
+        Elasticsearch {
+            client,
+            bulk_url: format!("{}/_bulk", url.trim_end_matches('/')),
+            index: index.to_string(),
+            api_key,
+            format,
+            retry,
+            progress,
+        }
+    }
+
+    fn index(&self, document: &Document) -> String {
+        self.index
+            .replace("{dataset}", &document.dataset_id)
+            .replace("{assembly}", &document.assembly_id)
+            .to_lowercase()
+    }
+
+    /// Body of a bulk request, with an index action before each variant.
+    fn body(&self, payloads: &[Payload]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for payload in payloads {
+            let json = self.format.json(payload);
+            let document: Document = serde_json::from_slice(&json).unwrap();
+            let mut action = json!({ "_index": self.index(&document) });
+            if let Some(id) = document.id {
+                action["_id"] = json!(id);
+            }
+            serde_json::to_writer(&mut body, &json!({ "index": action })).unwrap();
+            body.push(b'\n');
+            body.extend_from_slice(&json);
+            body.push(b'\n');
+        }
+        body
+    }
+
+    /// Index variants, retrying on connection errors, 429 and 5xx
+    /// responses. Exits if any variant is rejected.
+    pub fn send(&self, payloads: &[Payload]) {
+        let body = self.body(payloads);
+        let mut attempt = 0;
+        let res = loop {
+            let mut req = self
+                .client
+                .post(&self.bulk_url)
+                .header(CONTENT_TYPE, "application/x-ndjson");
+            if let Some(key) = &self.api_key {
+                req = req.header(AUTHORIZATION, format!("ApiKey {}", key));
+            }
+            let result = req.body(body.clone()).send();
+            if let Ok(res) = &result {
+                self.progress.response(res.status().as_u16());
+            }
+            let (error, exit) = match result {
+                Ok(res) if res.status().is_server_error() || res.status().as_u16() == 429 => (
+                    format!("Elasticsearch responded with {}", res.status()),
+                    Exit::UploadFailed,
+                ),
+                Ok(res) => break res,
+                Err(e) => (request_error(&e), Exit::for_error(&e)),
+            };
+            self.progress.failed();
+            if attempt >= self.retry.retries {
+                exit.fail(error);
+            }
+            warn!("{}, retrying", error);
+            thread::sleep(self.retry.delay(attempt));
+            attempt += 1;
+        };
+
+        if !res.status().is_success() {
+            rejected(res);
+        }
+        let bulk: BulkResponse = res.json().unwrap();
+        if bulk.errors {
+            let error = bulk
+                .items
+                .iter()
+                .filter_map(|item| item["index"].get("error"))
+                .next();
+            Exit::UploadFailed.fail(format!(
+                "Elasticsearch rejected variants: {}",
+                error.unwrap_or(&Value::Null)
+            ));
+        }
+        self.progress.uploaded(payloads.len() as u64);
+    }
+}
@@ -0,0 +1,65 @@
+use std::str::Utf8Error;
+use thiserror::Error;
+
+/// Reasons a VCF record can't be turned into a variant.
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("missing CHROM")]
+    MissingChrom,
+    #[error("missing REF")]
+    MissingRef,
+    #[error("missing INFO/{0}")]
+    MissingInfo(&'static str),
+    #[error("invalid UTF-8 in {field}: {source}")]
+    InvalidUtf8 { field: String, source: Utf8Error },
+    #[error("contig {0} not in reference FASTA")]
+    UnknownContig(String),
+    #[error("REF {found} does not match reference {expected}")]
+    RefMismatch { found: String, expected: String },
+    #[error("can't lift over: {0}")]
+    Liftover(&'static str),
+    #[error("invalid breakend {0}")]
+    MalformedBreakend(String),
+    #[error("INFO/{field} has {found} values for {expected} ALT alleles")]
+    AlleleMismatch {
+        field: &'static str,
+        found: usize,
+        expected: usize,
+    },
+    #[error("ANN entry has no field {index}: {ann}")]
+    MalformedAnn { index: usize, ann: String },
+    #[error("can't convert {value} for mapped field {field}")]
+    InvalidMapping { field: String, value: String },
+    #[error("script error: {0}")]
+    Script(String),
+    #[error("{0}")]
+    Htslib(#[from] rust_htslib::errors::Error),
+}
+
+impl Error {
+    /// Error message without the details of a particular record, to count
+    /// errors of the same kind together.
+    pub fn kind(&self) -> String {
+        match self {
+            Error::MissingChrom => "missing CHROM".to_string(),
+            Error::MissingRef => "missing REF".to_string(),
+            Error::MissingInfo(tag) => format!("missing INFO/{}", tag),
+            Error::InvalidUtf8 { field, .. } => format!("invalid UTF-8 in {}", field),
+            Error::UnknownContig(_) => "contig not in reference".to_string(),
+            Error::RefMismatch { .. } => "REF does not match reference".to_string(),
+            Error::Liftover(reason) => format!("can't lift over: {}", reason),
+            Error::MalformedBreakend(_) => "invalid breakend".to_string(),
+            Error::AlleleMismatch { field, .. } => {
+                format!("INFO/{} does not match ALT alleles", field)
+            }
+            Error::MalformedAnn { .. } => "malformed ANN".to_string(),
+            Error::InvalidMapping { field, .. } => {
+                format!("can't convert mapped field {}", field)
+            }
+            Error::Script(_) => "script error".to_string(),
+            Error::Htslib(e) => e.to_string(),
+        }
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,86 @@
+use log::error;
+use reqwest::StatusCode;
+use std::any::Any;
+use std::fmt::Display;
+use std::panic::{self, AssertUnwindSafe};
+use std::process;
+
+/// Exit codes telling workflow engines why brave-import failed. Other
+/// errors panic and exit with 101.
+#[derive(Clone, Copy)]
+pub enum Exit {
+    /// Records that can't be converted, with `validate` or `import --dryrun`.
+    ValidationFailed = 2,
+    /// Variants rejected by the server once retries are exhausted.
+    UploadFailed = 3,
+    /// Credentials rejected by the server.
+    AuthFailed = 4,
+    /// No connection to the server once retries are exhausted.
+    Unreachable = 5,
+}
+
+impl Exit {
+    /// Code for a request that failed without a response.
+    pub fn for_error(e: &reqwest::Error) -> Self {
+        if e.is_connect() || e.is_timeout() {
+            Exit::Unreachable
+        } else {
+            Exit::UploadFailed
+        }
+    }
+
+    /// Code for an unsuccessful response.
+    pub fn for_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Exit::AuthFailed,
+            _ => Exit::UploadFailed,
+        }
+    }
+
+    /// Stop with this code. This unwinds like a panic, so an upload worker
+    /// can hand the failure back to `import`, which writes the summary
+    /// before `main` exits with the code.
+    pub fn fail(self, message: impl Display) -> ! {
+        panic::panic_any(Failure {
+            exit: self,
+            message: message.to_string(),
+        })
+    }
+}
+
+/// Payload of the panic raised by `Exit::fail`.
+struct Failure {
+    exit: Exit,
+    message: String,
+}
+
+/// Log failures as errors, leaving the message of other panics to the
+/// default hook.
+pub fn install_hook() {
+    let default = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        match info.payload().downcast_ref::<Failure>() {
+            Some(failure) => error!("{}", failure.message),
+            None => default(info),
+        }
+    }));
+}
+
+/// Run a command, exiting with the code of its failure, if any.
+pub fn run(command: impl FnOnce()) {
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(command)) {
+        exit(payload)
+    }
+}
+
+/// Exit with the code of a failure, or unwind again with the payload of
+/// another panic.
+fn exit(payload: Box<dyn Any + Send>) -> ! {
+    match payload.downcast::<Failure>() {
+        Ok(failure) => {
+            log::logger().flush();
+            process::exit(failure.exit as i32)
+        }
+        Err(payload) => panic::resume_unwind(payload),
+    }
+}
@@ -0,0 +1,169 @@
+use crate::bigquery;
+use crate::cli::{DatasetOpts, ExportOpts, InputOpts};
+use crate::columnar::{self, Batches};
+use crate::duckdb::DuckDb;
+use crate::ndjson;
+use crate::reader::VariantReader;
+use crate::table::Table;
+use crate::variant::Variant;
+use arrow::ipc::writer::StreamWriter;
+use clap::ArgEnum;
+use log::info;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+/// File format of the exported variants.
+#[derive(ArgEnum, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    /// One JSON variant per line, as sent to the server.
+    Ndjson,
+    /// Columnar file with nested lists and structs, for Spark or DuckDB.
+    Parquet,
+    /// Arrow IPC stream, for Arrow tooling in Rust or Python.
+    Arrow,
+    /// Flat table with one row per ALT allele, for spreadsheets or R.
+    Csv,
+    /// Like CSV, separated by tabs.
+    Tsv,
+    /// Table of a DuckDB database, replacing the variants of the dataset.
+    Duckdb,
+}
+
+impl ExportFormat {
+    /// DuckDB for files ending with `.duckdb`, else this format.
+    pub fn detect(self, path: &Path) -> Self {
+        if path.extension().is_some_and(|x| x == "duckdb") {
+            ExportFormat::Duckdb
+        } else {
+            self
+        }
+    }
+}
+
+/// Writer of variants in one of the export formats.
+pub enum Writer {
+    Ndjson(Box<dyn Write + Send>),
+    Parquet(ArrowWriter<File>, Batches),
+    Arrow(StreamWriter<Box<dyn Write + Send>>, Batches),
+    Table(Table),
+    DuckDb(DuckDb),
+}
+
+impl Writer {
+    pub fn create(
+        path: &Path,
+        format: ExportFormat,
+        input: &InputOpts,
+        dataset: &DatasetOpts,
+    ) -> Self {
+        let create =
+            || ndjson::create(path).unwrap_or_else(|e| panic!("Error creating {:?}: {}", path, e));
+        match format {
+            ExportFormat::Ndjson => Writer::Ndjson(create()),
+            ExportFormat::Arrow => {
+                let schema = columnar::schema();
+                let writer = StreamWriter::try_new(create(), &schema)
+                    .unwrap_or_else(|e| panic!("Error creating {:?}: {}", path, e));
+                Writer::Arrow(writer, Batches::new(schema))
+            }
+            ExportFormat::Csv | ExportFormat::Tsv => {
+                let delimiter = if format == ExportFormat::Csv {
+                    ','
+                } else {
+                    '\t'
+                };
+                let mut table = Table::new(create(), delimiter, input);
+                table.write_header().unwrap();
+                Writer::Table(table)
+            }
+            ExportFormat::Parquet => {
+                assert!(
+                    path != Path::new("-"),
+                    "Parquet can't be written to stdout, use --output"
+                );
+                let file = File::create(path)
+                    .unwrap_or_else(|e| panic!("Error creating {:?}: {}", path, e));
+                let schema = columnar::schema();
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                let writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))
+                    .unwrap_or_else(|e| panic!("Error creating {:?}: {}", path, e));
+                Writer::Parquet(writer, Batches::new(schema))
+            }
+            ExportFormat::Duckdb => {
+                let db = DuckDb::open(path, &dataset.dataset, &dataset.assembly)
+                    .unwrap_or_else(|e| panic!("Error opening {:?}: {}", path, e));
+                Writer::DuckDb(db)
+            }
+        }
+    }
+
+    /// Write a variant, given as JSON too when the caller has it at hand.
+    pub fn write(&mut self, v: &Variant, json: Option<&[u8]>) {
+        match self {
+            Writer::Ndjson(out) => match json {
+                Some(json) => ndjson::write_line(out, json).unwrap(),
+                None => ndjson::write_line(out, &serde_json::to_vec(v).unwrap()).unwrap(),
+            },
+            Writer::Parquet(writer, batches) => {
+                if let Some(batch) = batches.push(v).unwrap() {
+                    writer.write(&batch).unwrap();
+                }
+            }
+            Writer::Arrow(writer, batches) => {
+                if let Some(batch) = batches.push(v).unwrap() {
+                    writer.write(&batch).unwrap();
+                }
+            }
+            Writer::Table(table) => table.write(v).unwrap(),
+            Writer::DuckDb(db) => db.write(v).unwrap(),
+        }
+    }
+
+    pub fn finish(self) {
+        match self {
+            Writer::Ndjson(mut out) => out.flush().unwrap(),
+            Writer::Parquet(mut writer, mut batches) => {
+                if let Some(batch) = batches.flush().unwrap() {
+                    writer.write(&batch).unwrap();
+                }
+                writer.close().unwrap();
+            }
+            Writer::Arrow(mut writer, mut batches) => {
+                if let Some(batch) = batches.flush().unwrap() {
+                    writer.write(&batch).unwrap();
+                }
+                writer.finish().unwrap();
+                writer.into_inner().unwrap().flush().unwrap();
+            }
+            Writer::Table(mut table) => table.flush().unwrap(),
+            Writer::DuckDb(db) => db.finish().unwrap(),
+        }
+    }
+}
+
+pub fn run(opts: ExportOpts) {
+    if let Some(path) = &opts.bigquery_schema {
+        let schema = serde_json::to_string_pretty(&bigquery::schema()).unwrap();
+        fs::write(path, schema).unwrap_or_else(|e| panic!("Error writing {:?}: {}", path, e));
+    }
+
+    let format = opts.output_format.detect(&opts.output);
+    let mut writer = Writer::create(&opts.output, format, &opts.input, &opts.dataset);
+
+    let mut reader = VariantReader::new(&opts.input, opts.dataset.dataset, opts.dataset.assembly);
+
+    for (_, v) in reader.by_ref() {
+        writer.write(&v, None);
+    }
+    writer.finish();
+
+    for line in reader.summary().lines() {
+        info!("{}", line);
+    }
+}
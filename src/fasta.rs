@@ -0,0 +1,66 @@
+use rust_htslib::htslib as hts;
+use std::ffi::CString;
+use std::io;
+use std::path::Path;
+use std::slice;
+
+/// Indexed FASTA file, read through htslib's faidx. rust-htslib's own
+/// wrapper leaks every fetched sequence and crashes on unknown contigs.
+pub struct Fasta {
+    inner: *mut hts::faidx_t,
+}
+
+impl Fasta {
+    /// Open a FASTA file, building its `.fai` index if missing.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, "invalid path");
+        let cpath = CString::new(path.to_str().ok_or_else(invalid)?).map_err(|_| invalid())?;
+        // SAFETY: cpath is a valid C string.
+        let inner = unsafe { hts::fai_load(cpath.as_ptr()) };
+        if inner.is_null() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "can't open FASTA file or its index",
+            ));
+        }
+        Ok(Fasta { inner })
+    }
+
+    /// Length of a contig, or None if it is not in the file.
+    pub fn len(&self, contig: &str) -> Option<u64> {
+        let name = CString::new(contig).ok()?;
+        // SAFETY: name is a valid C string.
+        let len = unsafe { hts::faidx_seq_len(self.inner, name.as_ptr()) };
+        u64::try_from(len).ok()
+    }
+
+    /// Bases from start to end, 0-based inclusive, or None if the contig is
+    /// not in the file.
+    pub fn fetch(&self, contig: &str, start: u64, end: u64) -> Option<Vec<u8>> {
+        let name = CString::new(contig).ok()?;
+        let mut len: hts::hts_pos_t = 0;
+        // SAFETY: the returned buffer holds len bytes and is freed once copied.
+        unsafe {
+            let ptr = hts::faidx_fetch_seq64(
+                self.inner,
+                name.as_ptr(),
+                start as hts::hts_pos_t,
+                end as hts::hts_pos_t,
+                &mut len,
+            );
+            if ptr.is_null() || len < 0 {
+                return None;
+            }
+            let seq = slice::from_raw_parts(ptr as *const u8, len as usize).to_vec();
+            hts::hts_free(ptr as *mut _);
+            Some(seq)
+        }
+    }
+}
+
+impl Drop for Fasta {
+    fn drop(&mut self) {
+        // SAFETY: inner was returned by fai_load and is destroyed only here.
+        unsafe { hts::fai_destroy(self.inner) }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::annotation::AnnotationFile;
+use crate::error::Result;
+use crate::variant::{get_info_field, has_info, Variant};
+use rust_htslib::bcf::record::Numeric;
+use rust_htslib::bcf::Record;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Frequency of a variant in gnomAD.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct Gnomad {
+    #[serde(rename = "alleleFrequency")]
+    pub allele_frequency: Option<f32>,
+    /// Highest frequency among populations, and that population.
+    #[serde(rename = "popmaxFrequency")]
+    pub popmax_frequency: Option<f32>,
+    pub popmax: Option<String>,
+}
+
+/// Value for ALT allele alt of a Number=A Float INFO field.
+fn float(record: &Record, tag: &str, alt: usize) -> Result<Option<f32>> {
+    if !has_info(record, tag) {
+        return Ok(None);
+    }
+    let values = record.info(tag.as_bytes()).float()?;
+    Ok(values
+        .and_then(|x| x.get(alt).copied())
+        .filter(|x| !x.is_missing()))
+}
+
+fn string(record: &Record, tag: &str, alt: usize) -> Result<Option<String>> {
+    if !has_info(record, tag) {
+        return Ok(None);
+    }
+    Ok(get_info_field(record, tag)?.and_then(|x| x.get(alt).cloned()))
+}
+
+/// Add the gnomAD frequencies of the record of the same variant. Popmax
+/// fields are named `AF_popmax` in gnomAD 2 and `AF_grpmax` since gnomAD 4.
+pub fn annotate(gnomad: &mut AnnotationFile, v: &mut Variant) -> Result<()> {
+    let (record, alt) = match gnomad.find(v)? {
+        Some(found) => found,
+        None => return Ok(()),
+    };
+    let popmax_frequency = match float(&record, "AF_popmax", alt)? {
+        Some(af) => Some(af),
+        None => float(&record, "AF_grpmax", alt)?,
+    };
+    let popmax = match string(&record, "popmax", alt)? {
+        Some(popmax) => Some(popmax),
+        None => string(&record, "grpmax", alt)?,
+    };
+    v.gnomad = Some(Gnomad {
+        allele_frequency: float(&record, "AF", alt)?,
+        popmax_frequency,
+        popmax,
+    });
+    Ok(())
+}
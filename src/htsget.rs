@@ -0,0 +1,94 @@
+use crate::region::Region;
+use percent_encoding::percent_decode_str;
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{self, Write};
+use std::sync::OnceLock;
+use tempfile::TempPath;
+
+#[derive(Deserialize)]
+struct TicketResponse {
+    htsget: Ticket,
+}
+
+#[derive(Deserialize)]
+struct Ticket {
+    urls: Vec<Block>,
+}
+
+#[derive(Deserialize)]
+struct Block {
+    url: String,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+}
+
+static CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// Download with client, built from the TLS options of the command, instead
+/// of a client with default settings. Must be called before the first
+/// download.
+pub fn set_client(client: Client) {
+    let _ = CLIENT.set(client);
+}
+
+/// Download the VCF slice covering region, or the whole VCF, from an htsget
+/// endpoint such as `https://htsget.example.org/variants/NA12878`. Blocks
+/// listed in the ticket are concatenated into a temporary file, which is
+/// removed when the returned path is dropped, or right away on errors.
+pub fn download(endpoint: &str, region: Option<&Region>) -> Result<TempPath, Box<dyn Error>> {
+    let client = CLIENT.get_or_init(Client::new);
+
+    let mut query = vec![("format", "VCF".to_string())];
+    if let Some(region) = region {
+        query.push(("referenceName", region.contig.clone()));
+        query.push(("start", region.start.to_string()));
+        if let Some(end) = region.end {
+            // htsget end is exclusive
+            query.push(("end", (end + 1).to_string()));
+        }
+    }
+
+    let res = client.get(endpoint).query(&query).send()?;
+    if !res.status().is_success() {
+        return Err(format!("htsget server responded {}: {}", res.status(), res.text()?).into());
+    }
+    let ticket: TicketResponse = res.json()?;
+
+    let mut file = tempfile::Builder::new()
+        .prefix("brave-import-")
+        .suffix(".vcf.gz")
+        .tempfile()?;
+
+    for block in ticket.htsget.urls {
+        if let Some(data) = block.url.strip_prefix("data:") {
+            file.write_all(&decode_data_url(data)?)?;
+            continue;
+        }
+
+        let mut req = client.get(&block.url);
+        for (name, value) in &block.headers {
+            req = req.header(name.as_str(), value.as_str());
+        }
+        let mut res = req.send()?;
+        if !res.status().is_success() {
+            return Err(format!("Error fetching {}: {}", block.url, res.status()).into());
+        }
+        io::copy(&mut res, &mut file)?;
+    }
+
+    Ok(file.into_temp_path())
+}
+
+/// Decode the part after `data:` of a data URL, which htsget servers use to
+/// inline small blocks such as the VCF header.
+fn decode_data_url(data: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let (media_type, content) = data.split_once(',').ok_or("Invalid data URL")?;
+    if media_type.ends_with(";base64") {
+        Ok(base64::decode(content)?)
+    } else {
+        Ok(percent_decode_str(content).collect())
+    }
+}
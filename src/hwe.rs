@@ -0,0 +1,95 @@
+/// P-value of the exact test of Wigginton, Cutler and Abecasis (2005), the
+/// probability of a heterozygote count at most as likely as the observed
+/// one given the allele counts.
+pub fn exact_test(het: u32, hom_ref: u32, hom_alt: u32) -> f64 {
+    let hom_rare = hom_ref.min(hom_alt) as usize;
+    let hom_common = hom_ref.max(hom_alt) as usize;
+    let het = het as usize;
+    let n = het + hom_rare + hom_common;
+    if n == 0 {
+        return 1.0;
+    }
+    let rare = 2 * hom_rare + het;
+
+    // Start from the most likely heterozygote count, which has the same
+    // parity as the rare allele count.
+    let mut mid = rare * (2 * n - rare) / (2 * n);
+    if (rare ^ mid) & 1 == 1 {
+        mid += 1;
+    }
+    let mut probs = vec![0.0; rare + 1];
+    probs[mid] = 1.0;
+
+    let (mut hets, mut rares, mut commons) = (mid, (rare - mid) / 2, n - mid - (rare - mid) / 2);
+    while hets >= 2 {
+        probs[hets - 2] =
+            probs[hets] * (hets * (hets - 1)) as f64 / (4 * (rares + 1) * (commons + 1)) as f64;
+        hets -= 2;
+        rares += 1;
+        commons += 1;
+    }
+
+    let (mut hets, mut rares, mut commons) = (mid, (rare - mid) / 2, n - mid - (rare - mid) / 2);
+    while hets + 2 <= rare {
+        probs[hets + 2] =
+            probs[hets] * (4 * rares * commons) as f64 / ((hets + 2) * (hets + 1)) as f64;
+        hets += 2;
+        rares -= 1;
+        commons -= 1;
+    }
+
+    let total: f64 = probs.iter().sum();
+    let observed = probs[het];
+    let p: f64 = probs.iter().filter(|&&x| x <= observed).sum();
+    (p / total).min(1.0)
+}
+
+/// Inbreeding coefficient F, one minus the ratio of observed to expected
+/// heterozygotes. None for monomorphic sites.
+pub fn inbreeding_coefficient(het: u32, hom_ref: u32, hom_alt: u32) -> Option<f64> {
+    let n = (het + hom_ref + hom_alt) as f64;
+    let p = (2 * hom_ref + het) as f64 / (2.0 * n);
+    let expected = 2.0 * p * (1.0 - p) * n;
+    if n == 0.0 || expected == 0.0 {
+        return None;
+    }
+    Some(1.0 - het as f64 / expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() <= expected * 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn exact_test_matches_reference_values() {
+        assert_close(exact_test(0, 99, 1), 1.0 / 199.0);
+        assert_close(exact_test(50, 25, 25), 1.0);
+        assert_close(exact_test(10, 85, 5), 0.0005043374845285301);
+        assert_close(exact_test(57, 14, 29), 0.15068007651576093);
+        assert!(exact_test(0, 50, 50) < 1e-29);
+    }
+
+    #[test]
+    fn exact_test_is_symmetric_in_homozygotes() {
+        assert_eq!(exact_test(10, 85, 5), exact_test(10, 5, 85));
+        assert_eq!(exact_test(0, 0, 0), 1.0);
+        assert_eq!(exact_test(0, 100, 0), 1.0);
+    }
+
+    #[test]
+    fn inbreeding_coefficient_compares_observed_and_expected_heterozygotes() {
+        assert_eq!(inbreeding_coefficient(50, 25, 25), Some(0.0));
+        assert_eq!(inbreeding_coefficient(0, 50, 50), Some(1.0));
+        assert_eq!(inbreeding_coefficient(100, 0, 0), Some(-1.0));
+        assert_eq!(inbreeding_coefficient(0, 100, 0), None);
+    }
+}
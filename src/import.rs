@@ -0,0 +1,289 @@
+use crate::cli::ImportOpts;
+use crate::dataset;
+use crate::exit::Exit;
+use crate::export::{ExportFormat, Writer};
+use crate::htsget;
+use crate::ledger::Entry;
+use crate::ndjson;
+use crate::progress::Progress;
+use crate::reader::{header_contigs, indexed_record_count, Counts, VariantReader};
+use crate::region::Region;
+use crate::report::Report;
+use crate::summary::Summary;
+use crate::upload::{RateLimiter, WireFormat, WorkerPool};
+use log::info;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+type Output = Mutex<Writer>;
+
+pub fn run(opts: ImportOpts) {
+    let started = Instant::now();
+    if opts.input.htsget {
+        htsget::set_client(opts.server.transport_client());
+    }
+    let dataset_id = opts.dataset.dataset.clone();
+    let assemble_id = opts.dataset.assembly.clone();
+    let output = opts.output.as_ref().map(|path| {
+        let format = ExportFormat::Ndjson.detect(path);
+        Mutex::new(Writer::create(path, format, &opts.input, &opts.dataset))
+    });
+
+    if !opts.dryrun && opts.upload.uses_server() {
+        dataset::ensure_exists(
+            &opts.server,
+            &opts.dataset,
+            opts.create_dataset,
+            opts.dataset_description.as_deref(),
+        );
+    }
+
+    let progress = progress(&opts);
+    let limiter = opts.upload.rate_limiter();
+
+    let (report, counts, already_imported, result) = if opts.parallel_contigs > 1 {
+        run_parallel(&opts, output.as_ref(), &limiter, &progress)
+    } else {
+        let mut reader = VariantReader::new(&opts.input, dataset_id, assemble_id);
+        reader.set_progress(progress.clone());
+        reader.report = new_report(&opts);
+
+        if let Some(position) = opts.upload.resume_position() {
+            reader.resume_from(position);
+        }
+
+        let (already_imported, result) =
+            import_shard(&opts, &mut reader, &limiter, &progress, output.as_ref());
+
+        progress.finish();
+        for line in reader.summary().lines() {
+            info!("{}", line);
+        }
+        (reader.report, reader.counts, already_imported, result)
+    };
+
+    if let Some(report) = report {
+        write_report(&opts, &report);
+    }
+
+    // The output of a failed import is left unfinished, so a DuckDB
+    // table keeps the variants of the last complete import.
+    if let (Some(out), Ok(())) = (output, &result) {
+        out.into_inner().unwrap().finish();
+    }
+
+    let summary = Summary::new(&counts, &progress, already_imported, started.elapsed());
+    for line in summary.contig_lines() {
+        info!("{}", line);
+    }
+    if let Some(path) = &opts.summary_file {
+        summary.write(path);
+    }
+
+    if let Err(payload) = result {
+        panic::resume_unwind(payload);
+    }
+
+    if opts.dryrun && counts.skipped > 0 {
+        Exit::ValidationFailed.fail(format!("{} records can't be converted", counts.skipped));
+    }
+}
+
+/// Import the variants of a reader, returning the number of variants
+/// skipped as already imported and the first failure, of the reader or of
+/// an upload worker, to raise again once the summary is written.
+fn import_shard(
+    opts: &ImportOpts,
+    reader: &mut VariantReader,
+    limiter: &RateLimiter,
+    progress: &Progress,
+    output: Option<&Output>,
+) -> (u64, thread::Result<()>) {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut pool = opts
+            .upload
+            .start(&opts.server, limiter.clone(), progress.clone());
+        import(opts, reader, &mut pool, output);
+        pool.join()
+    }));
+    result.unwrap_or_else(|payload| (0, Err(payload)))
+}
+
+/// Run one reader and upload pipeline per contig, parallel_contigs at a time.
+/// Returns the report, the counts and the number of variants skipped as
+/// already imported of all contigs, and the first failure, after which no
+/// other contig is started.
+fn run_parallel(
+    opts: &ImportOpts,
+    output: Option<&Output>,
+    limiter: &RateLimiter,
+    progress: &Progress,
+) -> (Option<Report>, Counts, u64, thread::Result<()>) {
+    let shards = Mutex::new(shards(opts));
+    let counts = Mutex::new(Counts::default());
+    let report = Mutex::new(new_report(opts));
+    let already_imported = Mutex::new(0);
+    let result = Mutex::new(Ok(()));
+
+    thread::scope(|s| {
+        for _ in 0..opts.parallel_contigs {
+            s.spawn(|| loop {
+                if result.lock().unwrap().is_err() {
+                    return;
+                }
+                let regions = match shards.lock().unwrap().pop_front() {
+                    Some(regions) => regions,
+                    None => return,
+                };
+                let mut reader = VariantReader::with_regions(
+                    &opts.input,
+                    opts.dataset.dataset.clone(),
+                    opts.dataset.assembly.clone(),
+                    regions,
+                );
+                reader.set_progress(progress.clone());
+                reader.report = new_report(opts);
+                // Failures are handed back rather than left to the scope,
+                // which would replace them with a panic of its own.
+                let (n, shard_result) = import_shard(opts, &mut reader, limiter, progress, output);
+                *already_imported.lock().unwrap() += n;
+                let mut first = result.lock().unwrap();
+                if first.is_ok() {
+                    *first = shard_result;
+                }
+                drop(first);
+                counts.lock().unwrap().merge(&reader.counts);
+                if let (Some(report), Some(shard)) = (&mut *report.lock().unwrap(), reader.report) {
+                    report.merge(shard);
+                }
+            });
+        }
+    });
+
+    progress.finish();
+    let do_filter = !opts.input.dont_filter;
+    let counts = counts.into_inner().unwrap();
+    for line in counts.summary(do_filter).lines() {
+        info!("{}", line);
+    }
+    (
+        report.into_inner().unwrap(),
+        counts,
+        already_imported.into_inner().unwrap(),
+        result.into_inner().unwrap(),
+    )
+}
+
+/// Validation report collected on dry runs.
+fn new_report(opts: &ImportOpts) -> Option<Report> {
+    opts.dryrun.then(|| {
+        Report::new(
+            opts.report_examples,
+            &opts.input.coverage_tag,
+            &opts.input.gq_tag,
+        )
+    })
+}
+
+fn write_report(opts: &ImportOpts, report: &Report) {
+    let path = &opts.report;
+    let mut out =
+        ndjson::create(path).unwrap_or_else(|e| panic!("Error creating {:?}: {}", path, e));
+    serde_json::to_writer_pretty(&mut out, report).unwrap();
+    writeln!(out).unwrap();
+    out.flush().unwrap();
+}
+
+/// Progress bar sized by the number of records in the indexes of the VCF
+/// files. When reading regions or from files without index, only the number
+/// of records read so far is shown.
+fn progress(opts: &ImportOpts) -> Progress {
+    if opts.no_progress || opts.debug {
+        return Progress::hidden();
+    }
+    if opts.input.htsget || !opts.input.regions().is_empty() {
+        return Progress::new(None);
+    }
+    let total = opts
+        .input
+        .vcf_files()
+        .iter()
+        .map(|path| indexed_record_count(path))
+        .sum();
+    Progress::new(total)
+}
+
+/// Regions to import grouped by contig. Without regions, every contig
+/// declared in the header of the first file is imported.
+fn shards(opts: &ImportOpts) -> VecDeque<Vec<Region>> {
+    let mut regions = opts.input.regions();
+    if regions.is_empty() {
+        let path = opts.input.vcf_files().into_iter().next().unwrap();
+        let contigs =
+            header_contigs(&path).unwrap_or_else(|e| panic!("Error opening {}: {}", path, e));
+        regions = contigs
+            .into_iter()
+            .map(|contig| Region {
+                contig,
+                start: 0,
+                end: None,
+            })
+            .collect();
+    }
+
+    let mut shards: VecDeque<Vec<Region>> = VecDeque::new();
+    for region in regions {
+        match shards.iter_mut().find(|x| x[0].contig == region.contig) {
+            Some(shard) => shard.push(region),
+            None => shards.push_back(vec![region]),
+        }
+    }
+    shards
+}
+
+fn import(
+    opts: &ImportOpts,
+    reader: &mut VariantReader,
+    pool: &mut WorkerPool,
+    output: Option<&Output>,
+) {
+    for (position, v) in reader {
+        if opts.debug {
+            eprintln!("{:?}", v);
+        }
+
+        if opts.dryrun && output.is_none() {
+            continue;
+        }
+
+        let format = opts.upload.wire_format;
+        let json = (output.is_some() || format == WireFormat::Json)
+            .then(|| serde_json::to_vec(&v).unwrap());
+
+        if let Some(out) = output {
+            out.lock().unwrap().write(&v, json.as_deref());
+        }
+
+        if opts.dryrun {
+            continue;
+        }
+
+        let payload = match json {
+            Some(json) if format == WireFormat::Json => json,
+            _ => format.encode(&v),
+        };
+
+        let key = opts.upload.message_key(v.id.as_deref(), &position);
+        let entry = match (&opts.upload.ledger, v.id) {
+            (Some(_), Some(id)) => Some(Entry::new(id, v.dataset_id, &payload)),
+            _ => None,
+        };
+        if !pool.push(position, key, entry, payload) {
+            break;
+        }
+    }
+}
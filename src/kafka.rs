@@ -0,0 +1,119 @@
+use crate::checkpoint::Position;
+use crate::exit::Exit;
+use crate::progress::Progress;
+use crate::upload::{Payload, RetryPolicy};
+use clap::ArgEnum;
+use kafka::producer::{Producer, Record, RequiredAcks};
+use log::warn;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// Key of the messages, which decides their partition.
+#[derive(ArgEnum, Clone, Copy, PartialEq)]
+pub enum MessageKey {
+    /// Variant id, so that every import of a variant goes to the same
+    /// partition and compacted topics keep the last one.
+    Id,
+    /// Contig and position, keeping the alleles of a site together.
+    Locus,
+    /// No key, spreading messages over partitions.
+    None,
+}
+
+impl MessageKey {
+    pub fn key(&self, id: Option<&str>, position: &Position) -> Option<String> {
+        match self {
+            MessageKey::Id => id.map(String::from),
+            MessageKey::Locus => Some(format!("{}:{}", position.reference_name, position.start)),
+            MessageKey::None => None,
+        }
+    }
+}
+
+/// Acknowledgement the brokers give before a message counts as sent.
+#[derive(ArgEnum, Clone, Copy, PartialEq)]
+pub enum Acks {
+    /// Written to every in-sync replica.
+    All,
+    /// Written by the partition leader.
+    One,
+    /// Not acknowledged, messages may be lost.
+    None,
+}
+
+impl Acks {
+    fn required(&self) -> RequiredAcks {
+        match self {
+            Acks::All => RequiredAcks::All,
+            Acks::One => RequiredAcks::One,
+            Acks::None => RequiredAcks::None,
+        }
+    }
+}
+
+/// Publisher of serialized variants to a Kafka topic. A batch is sent at
+/// once and retried as a whole until every message is acknowledged, so
+/// messages are delivered at least once.
+pub struct Kafka {
+    producer: Mutex<Producer>,
+    topic: String,
+    retry: RetryPolicy,
+    progress: Progress,
+}
+
+impl Kafka {
+    pub fn new(
+        brokers: &[String],
+        topic: &str,
+        acks: Acks,
+        retry: RetryPolicy,
+        progress: Progress,
+    ) -> kafka::Result<Self> {
+        let producer = Producer::from_hosts(brokers.to_vec())
+            .with_ack_timeout(Duration::from_secs(30))
+            .with_required_acks(acks.required())
+            .create()?;
+        Ok(Kafka {
+            producer: Mutex::new(producer),
+            topic: topic.to_string(),
+            retry,
+            progress,
+        })
+    }
+
+    pub fn send(&self, keys: &[Option<String>], payloads: &[Payload]) {
+        let records: Vec<_> = keys
+            .iter()
+            .zip(payloads)
+            .map(|(key, payload)| {
+                let key = key.as_deref().unwrap_or_default().as_bytes();
+                Record::from_key_value(self.topic.as_str(), key, payload.as_slice())
+            })
+            .collect();
+
+        let mut attempt = 0;
+        loop {
+            let error = match self.producer.lock().unwrap().send_all(&records) {
+                Ok(confirms) => confirms
+                    .iter()
+                    .flat_map(|confirm| &confirm.partition_confirms)
+                    .find_map(|confirm| confirm.offset.err())
+                    .map(|code| format!("Broker responded with {:?}", code)),
+                Err(e) => Some(format!("Error sending to Kafka: {}", e)),
+            };
+            let error = match error {
+                Some(error) => error,
+                None => break,
+            };
+            self.progress.failed();
+            if attempt >= self.retry.retries {
+                Exit::UploadFailed.fail(error);
+            }
+            warn!("{}, retrying", error);
+            thread::sleep(self.retry.delay(attempt));
+            attempt += 1;
+        }
+        self.progress.uploaded(payloads.len() as u64);
+    }
+}
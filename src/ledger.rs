@@ -0,0 +1,74 @@
+use rusqlite::{params, Connection};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Variant uploaded with a given payload, identified by its id, which
+/// includes the dataset, and by the digest of the payload.
+pub struct Entry {
+    pub id: String,
+    pub dataset: String,
+    pub digest: String,
+}
+
+impl Entry {
+    pub fn new(id: String, dataset: String, payload: &[u8]) -> Self {
+        let digest = Sha256::digest(payload);
+        Entry {
+            id,
+            dataset,
+            digest: digest.iter().map(|x| format!("{:02x}", x)).collect(),
+        }
+    }
+}
+
+/// SQLite file recording the variants successfully uploaded, so that
+/// later imports can skip those whose payload didn't change.
+pub struct Ledger {
+    conn: Mutex<Connection>,
+}
+
+impl Ledger {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        // Shards of an import write to the same file.
+        conn.busy_timeout(Duration::from_secs(60))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS imported (
+                id TEXT PRIMARY KEY,
+                dataset TEXT NOT NULL,
+                digest TEXT NOT NULL
+            )",
+        )?;
+        Ok(Ledger {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Whether the variant was already uploaded with the same payload.
+    pub fn contains(&self, entry: &Entry) -> rusqlite::Result<bool> {
+        let conn = self.conn.lock().unwrap();
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM imported WHERE id = ?1 AND digest = ?2",
+            params![entry.id, entry.digest],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Record uploaded variants in a single transaction.
+    pub fn record(&self, entries: &[Entry]) -> rusqlite::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR REPLACE INTO imported (id, dataset, digest) VALUES (?1, ?2, ?3)",
+            )?;
+            for entry in entries {
+                stmt.execute(params![entry.id, entry.dataset, entry.digest])?;
+            }
+        }
+        tx.commit()
+    }
+}
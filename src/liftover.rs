@@ -0,0 +1,217 @@
+use crate::error::{Error, Result};
+use crate::variant::Variant;
+use flate2::read::MultiGzDecoder;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Ungapped alignment block of a chain, mapping source positions
+/// [start, end) to target positions starting at target_start. Target
+/// positions are counted from the end of the contig on reverse chains.
+struct Block {
+    start: u64,
+    end: u64,
+    target: Arc<Target>,
+    target_start: u64,
+}
+
+struct Target {
+    contig: String,
+    size: u64,
+    reverse: bool,
+}
+
+/// Coordinate mapping between assemblies read from a UCSC chain file, such
+/// as `hg19ToHg38.over.chain.gz`.
+pub struct Chain {
+    blocks: HashMap<String, Vec<Block>>,
+}
+
+impl Chain {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let reader: Box<dyn Read> = if path.extension().is_some_and(|x| x == "gz") {
+            Box::new(MultiGzDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
+        let invalid = |line: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid chain in {:?}: {}", path, line),
+            )
+        };
+
+        let mut blocks: HashMap<String, Vec<Block>> = HashMap::new();
+        // Source contig, next source and target positions of current chain.
+        let mut current: Option<(String, u64, Arc<Target>, u64)> = None;
+
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.is_empty() {
+                current = None;
+                continue;
+            }
+
+            if fields[0] == "chain" {
+                if fields.len() < 12 {
+                    return Err(invalid(&line));
+                }
+                let parse = |i: usize| fields[i].parse::<u64>().map_err(|_| invalid(&line));
+                let target = Target {
+                    contig: fields[7].to_string(),
+                    size: parse(8)?,
+                    reverse: fields[9] == "-",
+                };
+                current = Some((
+                    fields[2].to_string(),
+                    parse(5)?,
+                    Arc::new(target),
+                    parse(10)?,
+                ));
+                continue;
+            }
+
+            let (contig, start, target, target_start) =
+                current.as_mut().ok_or_else(|| invalid(&line))?;
+            let numbers = fields
+                .iter()
+                .map(|x| x.parse::<u64>().map_err(|_| invalid(&line)))
+                .collect::<io::Result<Vec<u64>>>()?;
+            let size = numbers[0];
+            blocks.entry(contig.clone()).or_default().push(Block {
+                start: *start,
+                end: *start + size,
+                target: Arc::clone(target),
+                target_start: *target_start,
+            });
+            // Gaps in source and target follow every block but the last.
+            *start += size + numbers.get(1).unwrap_or(&0);
+            *target_start += size + numbers.get(2).unwrap_or(&0);
+        }
+
+        for contig_blocks in blocks.values_mut() {
+            contig_blocks.sort_by_key(|b| b.start);
+        }
+        Ok(Chain { blocks })
+    }
+
+    /// Map the 0-based interval [start, start + len) to the target assembly,
+    /// returning contig, 0-based start and whether the strand is reversed.
+    /// The interval must lie within a single block.
+    fn lift(&self, contig: &str, start: u64, len: u64) -> Option<(&str, u64, bool)> {
+        let blocks = self.blocks.get(contig)?;
+        let i = blocks.partition_point(|b| b.start <= start);
+        let block = blocks.get(i.checked_sub(1)?)?;
+        if start + len > block.end {
+            return None;
+        }
+        let target = &block.target;
+        let target_start = block.target_start + (start - block.start);
+        let start = if target.reverse {
+            target.size - target_start - len
+        } else {
+            target_start
+        };
+        Some((&target.contig, start, target.reverse))
+    }
+
+    /// Move variant to the coordinates of the target assembly, reverse
+    /// complementing alleles on reverse chains. Structural variants must lie
+    /// within a single block up to their END.
+    pub fn lift_variant(&self, v: &mut Variant) -> Result<()> {
+        // The mate position is also written in the ALT allele.
+        if v.mate.is_some() {
+            return Err(Error::Liftover("breakend"));
+        }
+        let sv_len = v.end.map_or(0, |end| end - v.start + 1).max(0) as usize;
+        let len = v.reference_bases.len().max(sv_len).max(1) as u64;
+        let (contig, start, reverse) = self
+            .lift(&v.reference_name, v.start as u64 - 1, len)
+            .ok_or(Error::Liftover("position not in chain file"))?;
+
+        if reverse {
+            // The padding base of indels would end up after the alleles.
+            if v.alternate_bases
+                .iter()
+                .any(|x| x.len() != v.reference_bases.len())
+            {
+                return Err(Error::Liftover("indel on reverse strand"));
+            }
+            v.reference_bases = reverse_complement(&v.reference_bases);
+            v.alternate_bases = v
+                .alternate_bases
+                .iter()
+                .map(|x| reverse_complement(x))
+                .collect();
+        }
+        v.reference_name = contig.to_string();
+        let start = start as i64 + 1;
+        v.end = v.end.map(|end| end + start - v.start);
+        v.start = start;
+        Ok(())
+    }
+}
+
+fn reverse_complement(bases: &str) -> String {
+    bases
+        .chars()
+        .rev()
+        .map(|b| match b {
+            'A' => 'T',
+            'C' => 'G',
+            'G' => 'C',
+            'T' => 'A',
+            'a' => 't',
+            'c' => 'g',
+            'g' => 'c',
+            't' => 'a',
+            b => b,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const CHAIN: &str = "\
+chain 1000 chr1 1000 + 0 100 chr1 1000 + 500 600 1
+100
+
+chain 1000 chr2 1000 + 100 300 chr2 600 - 50 240 2
+100 10 0
+90
+";
+
+    fn chain() -> Chain {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.over.chain");
+        fs::write(&path, CHAIN).unwrap();
+        Chain::load(&path).unwrap()
+    }
+
+    #[test]
+    fn forward_chains_shift_positions() {
+        let chain = chain();
+        assert_eq!(chain.lift("chr1", 10, 1), Some(("chr1", 510, false)));
+        assert_eq!(chain.lift("chr1", 100, 1), None);
+        assert_eq!(chain.lift("chr3", 10, 1), None);
+    }
+
+    #[test]
+    fn reverse_chains_count_from_the_end_of_the_target_contig() {
+        let chain = chain();
+        // Target 50 + 20 from the end of a 600 bp contig.
+        assert_eq!(chain.lift("chr2", 120, 1), Some(("chr2", 529, true)));
+        // Second block, after a 10 bp gap in the source only.
+        assert_eq!(chain.lift("chr2", 210, 2), Some(("chr2", 448, true)));
+        assert_eq!(chain.lift("chr2", 205, 1), None);
+        assert_eq!(chain.lift("chr2", 198, 3), None);
+        assert_eq!(reverse_complement("ACGtn"), "naCGT");
+    }
+}
@@ -0,0 +1,111 @@
+use clap::ArgEnum;
+use log::{LevelFilter, Log, Metadata, Record};
+use serde_json::json;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(ArgEnum, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+/// Writes log lines to stderr or to a file, as plain text or as one JSON
+/// object per line. Records of dependencies are only written when they are
+/// warnings or errors.
+struct Logger {
+    level: LevelFilter,
+    format: LogFormat,
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let level = if metadata.target().starts_with("brave_import") {
+            self.level
+        } else {
+            self.level.min(LevelFilter::Warn)
+        };
+        metadata.level() <= level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let timestamp = timestamp();
+        let line = match self.format {
+            LogFormat::Text => format!("{} {:5} {}", timestamp, record.level(), record.args()),
+            LogFormat::Json => json!({
+                "timestamp": timestamp,
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            })
+            .to_string(),
+        };
+
+        let mut out = self.out.lock().unwrap();
+        // Nowhere to report a failure to write the log.
+        let _ = writeln!(out, "{}", line);
+    }
+
+    fn flush(&self) {
+        let _ = self.out.lock().unwrap().flush();
+    }
+}
+
+/// Install the global logger, appending to file if given.
+pub fn init(level: LevelFilter, format: LogFormat, file: Option<&Path>) {
+    let out: Box<dyn Write + Send> = match file {
+        Some(path) => Box::new(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .unwrap_or_else(|e| panic!("Error opening {:?}: {}", path, e)),
+        ),
+        None => Box::new(io::stderr()),
+    };
+    let logger = Logger {
+        level,
+        format,
+        out: Mutex::new(out),
+    };
+    log::set_boxed_logger(Box::new(logger)).unwrap();
+    log::set_max_level(level);
+}
+
+/// Current UTC time in RFC 3339 format with milliseconds.
+fn timestamp() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let secs = now.as_secs();
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // Civil date from days since 1970-01-01, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z % 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+        now.subsec_millis()
+    )
+}
@@ -0,0 +1,40 @@
+use crate::cli::LoginOpts;
+use keyring::Entry;
+use log::{info, warn};
+
+const SERVICE: &str = "brave-import";
+
+/// Keyring entries are keyed by user name and server URL, so credentials
+/// for several servers can be stored side by side.
+fn entry(host: &str, username: &str) -> keyring::Result<Entry> {
+    Entry::new(SERVICE, &format!("{}@{}", username, host))
+}
+
+/// Password saved by `login` for username at host, if any.
+pub fn stored_password(host: &str, username: &str) -> Option<String> {
+    match entry(host, username).and_then(|entry| entry.get_password()) {
+        Ok(password) => Some(password),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            warn!("Error reading password from keyring: {}", e);
+            None
+        }
+    }
+}
+
+pub fn run(mut opts: LoginOpts) {
+    opts.server.prompt_password();
+    let host = opts.server.host();
+    let username = opts.server.username();
+    let password = opts
+        .server
+        .password
+        .as_ref()
+        .unwrap_or_else(|| panic!("Password required, use --password or BRAVE_PASSWORD"));
+
+    entry(host, username)
+        .and_then(|entry| entry.set_password(password))
+        .unwrap_or_else(|e| panic!("Error saving password to keyring: {}", e));
+
+    info!("Saved password of {} at {} to keyring", username, host);
+}
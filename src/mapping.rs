@@ -0,0 +1,204 @@
+use crate::error::{Error, Result};
+use crate::variant::{
+    format_values, get_info_field, has_info, Variant, VECTOR_END, VECTOR_END_FLOAT,
+};
+use rust_htslib::bcf::header::TagType;
+use rust_htslib::bcf::record::Numeric;
+use rust_htslib::bcf::Record;
+use schemars::schema_for;
+use serde::Deserialize;
+use serde_json::{Number, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Extra payload fields filled from INFO or FORMAT tags, read from a TOML
+/// file with a table for each field, e.g.
+///
+/// ```toml
+/// [fields.caddPhred]
+/// info = "CADD_PHRED"
+/// type = "float"
+/// default = 0.0
+/// ```
+#[derive(Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Mapping {
+    #[serde(default)]
+    pub fields: BTreeMap<String, FieldMapping>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FieldMapping {
+    info: Option<String>,
+    /// Summarized as the mean of the values of all samples.
+    format: Option<String>,
+    #[serde(rename = "type", default)]
+    field_type: FieldType,
+    /// Send every value as an array instead of the first one.
+    #[serde(default)]
+    list: bool,
+    /// Sent when the tag is missing from a record.
+    default: Option<Value>,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    #[default]
+    String,
+    Integer,
+    Float,
+    Flag,
+}
+
+impl Mapping {
+    pub fn load(path: &Path) -> Self {
+        let content =
+            fs::read_to_string(path).unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e));
+        let mapping: Mapping = toml::from_str(&content)
+            .unwrap_or_else(|e| panic!("Invalid mapping {:?}: {}", path, e));
+
+        let schema = schema_for!(Variant);
+        let properties = schema
+            .schema
+            .object
+            .map(|x| x.properties)
+            .unwrap_or_default();
+        for (name, field) in &mapping.fields {
+            if properties.contains_key(name) {
+                panic!(
+                    "Invalid mapping {:?}: {} is already a payload field",
+                    path, name
+                );
+            }
+            if field.info.is_some() == field.format.is_some() {
+                panic!(
+                    "Invalid mapping {:?}: {} needs either info or format",
+                    path, name
+                );
+            }
+        }
+        mapping
+    }
+
+    /// Values of the mapped fields found in a record or with a default.
+    pub fn values(
+        &self,
+        record: &Record,
+        mask: Option<&[bool]>,
+    ) -> Result<BTreeMap<String, Value>> {
+        let mut values = BTreeMap::new();
+        for (name, field) in &self.fields {
+            if let Some(value) = field.value(record, name, mask)? {
+                values.insert(name.clone(), value);
+            }
+        }
+        Ok(values)
+    }
+}
+
+impl FieldMapping {
+    fn value(&self, record: &Record, name: &str, mask: Option<&[bool]>) -> Result<Option<Value>> {
+        let raw = match (&self.info, &self.format) {
+            (Some(tag), _) => info_values(record, tag)?,
+            (_, Some(tag)) => format_mean(record, tag, mask)?,
+            _ => None,
+        };
+        let raw = match raw {
+            Some(raw) if !raw.is_empty() => raw,
+            _ => {
+                return Ok(match self.field_type {
+                    FieldType::Flag if self.default.is_none() => Some(Value::Bool(false)),
+                    _ => self.default.clone(),
+                })
+            }
+        };
+        if let FieldType::Flag = self.field_type {
+            return Ok(Some(Value::Bool(true)));
+        }
+        let mut values = raw
+            .iter()
+            .map(|x| coerce(x, self.field_type, name))
+            .collect::<Result<Vec<_>>>()?;
+        if self.list {
+            Ok(Some(Value::Array(values)))
+        } else {
+            Ok(Some(values.swap_remove(0)))
+        }
+    }
+}
+
+fn coerce(value: &str, field_type: FieldType, name: &str) -> Result<Value> {
+    let invalid = || Error::InvalidMapping {
+        field: name.to_string(),
+        value: value.to_string(),
+    };
+    Ok(match field_type {
+        FieldType::String | FieldType::Flag => Value::String(value.to_string()),
+        FieldType::Integer => value
+            .parse::<i64>()
+            .ok()
+            .or_else(|| {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|x| x.is_finite())
+                    .map(|x| x.round() as i64)
+            })
+            .map(Value::from)
+            .ok_or_else(invalid)?,
+        FieldType::Float => value
+            .parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(Value::Number)
+            .ok_or_else(invalid)?,
+    })
+}
+
+/// Non-missing values of an INFO tag as text, or None if it is missing or
+/// not declared in the header.
+fn info_values(record: &Record, tag: &str) -> Result<Option<Vec<String>>> {
+    if !has_info(record, tag) {
+        return Ok(None);
+    }
+    let mut info = record.info(tag.as_bytes());
+    Ok(match record.header().info_type(tag.as_bytes())?.0 {
+        TagType::Flag => info.flag()?.then(|| vec!["true".to_string()]),
+        TagType::Integer => info.integer()?.map(|x| {
+            x.iter()
+                .take_while(|&&x| x != VECTOR_END)
+                .filter(|x| !x.is_missing())
+                .map(|x| x.to_string())
+                .collect()
+        }),
+        TagType::Float => info.float()?.map(|x| {
+            x.iter()
+                .take_while(|x| x.to_bits() != VECTOR_END_FLOAT)
+                .filter(|x| !x.is_missing())
+                .map(|x| x.to_string())
+                .collect()
+        }),
+        TagType::String => {
+            get_info_field(record, tag)?.map(|x| x.into_iter().filter(|x| x != ".").collect())
+        }
+    })
+}
+
+/// Mean of the values of a numeric FORMAT tag in the samples used.
+fn format_mean(record: &Record, tag: &str, mask: Option<&[bool]>) -> Result<Option<Vec<String>>> {
+    if record.header().format_type(tag.as_bytes()).is_err() {
+        return Ok(None);
+    }
+    let values: Vec<f64> = format_values(record, tag, mask)?
+        .into_iter()
+        .flatten()
+        .collect();
+    if values.is_empty() {
+        return Ok(None);
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    Ok(Some(vec![mean.to_string()]))
+}
@@ -0,0 +1,77 @@
+use crate::exit::Exit;
+use crate::progress::Progress;
+use crate::upload::{Mode, Payload, WireFormat};
+use mongodb::bson::{doc, Document};
+use mongodb::options::{InsertManyOptions, ReplaceOptions};
+use mongodb::sync::{Client, Collection};
+
+/// Writer of variants straight to the MongoDB database of a BraVE server,
+/// for initial loads too large to go through its REST API. Documents are
+/// the variants as sent to the server, with the id as `_id` like the
+/// server stores them.
+pub struct MongoDb {
+    collection: Collection<Document>,
+    mode: Mode,
+    format: WireFormat,
+    progress: Progress,
+}
+
+impl MongoDb {
+    /// Connect to the database named in the URI, e.g.
+    /// `mongodb://localhost/brave`.
+    pub fn new(
+        uri: &str,
+        collection: &str,
+        mode: Mode,
+        format: WireFormat,
+        progress: Progress,
+    ) -> mongodb::error::Result<Self> {
+        let client = Client::with_uri_str(uri)?;
+        let database = client
+            .default_database()
+            .unwrap_or_else(|| panic!("No database in {}, e.g. mongodb://localhost/brave", uri));
+        Ok(MongoDb {
+            collection: database.collection(collection),
+            mode,
+            format,
+            progress,
+        })
+    }
+
+    fn document(&self, payload: &[u8]) -> Document {
+        let mut document: Document = serde_json::from_slice(&self.format.json(payload)).unwrap();
+        if let Some(id) = document.remove("id") {
+            document.insert("_id", id);
+        }
+        document
+    }
+
+    /// Insert variants in one unordered bulk write, or replace them one by
+    /// one when upserting. Panics on any write error, such as a variant
+    /// already in the collection when creating.
+    pub fn send(&self, payloads: &[Payload]) {
+        let documents: Vec<_> = payloads.iter().map(|p| self.document(p)).collect();
+        match self.mode {
+            Mode::Create => {
+                let options = InsertManyOptions::builder().ordered(false).build();
+                self.collection
+                    .insert_many(&documents, options)
+                    .unwrap_or_else(|e| {
+                        Exit::UploadFailed.fail(format!("Error inserting variants: {}", e))
+                    });
+            }
+            Mode::Upsert => {
+                let options = ReplaceOptions::builder().upsert(true).build();
+                for document in &documents {
+                    let id = document.get("_id").cloned().expect("Variant without id");
+                    self.collection
+                        .replace_one(doc! { "_id": id }, document, options.clone())
+                        .unwrap_or_else(|e| {
+                            Exit::UploadFailed.fail(format!("Error replacing variant: {}", e))
+                        });
+                }
+            }
+        }
+        self.progress.uploaded(payloads.len() as u64);
+    }
+}
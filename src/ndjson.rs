@@ -0,0 +1,24 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Open path for reading newline-delimited JSON, "-" meaning stdin.
+pub fn open(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    if path == Path::new("-") {
+        return Ok(Box::new(BufReader::new(io::stdin())));
+    }
+    Ok(Box::new(BufReader::new(File::open(path)?)))
+}
+
+/// Open path for writing newline-delimited JSON, "-" meaning stdout.
+pub fn create(path: &Path) -> io::Result<Box<dyn Write + Send>> {
+    if path == Path::new("-") {
+        return Ok(Box::new(BufWriter::new(io::stdout())));
+    }
+    Ok(Box::new(BufWriter::new(File::create(path)?)))
+}
+
+pub fn write_line(out: &mut dyn Write, payload: &[u8]) -> io::Result<()> {
+    out.write_all(payload)?;
+    out.write_all(b"\n")
+}
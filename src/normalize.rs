@@ -0,0 +1,142 @@
+use crate::error::{Error, Result};
+use crate::fasta::Fasta;
+use crate::variant::Variant;
+
+/// Trim bases shared by all alleles and left-align indels, as `bcftools
+/// norm` does, so the same variant called by different pipelines gets the
+/// same coordinates. Symbolic and spanning deletion alleles, and records
+/// without ALT alleles or with ALT alleles all equal to REF, are left alone.
+pub fn normalize(v: &mut Variant, fasta: &Fasta) -> Result<()> {
+    let is_sequence = |x: &str| !x.is_empty() && x.bytes().all(|b| b"ACGTNacgtn".contains(&b));
+    if v.alternate_bases.is_empty()
+        || !is_sequence(&v.reference_bases)
+        || !v.alternate_bases.iter().all(|x| is_sequence(x))
+        || v.alternate_bases
+            .iter()
+            .all(|x| x.eq_ignore_ascii_case(&v.reference_bases))
+    {
+        return Ok(());
+    }
+
+    let mut alleles: Vec<Vec<u8>> = std::iter::once(&v.reference_bases)
+        .chain(&v.alternate_bases)
+        .map(|x| x.to_ascii_uppercase().into_bytes())
+        .collect();
+    let mut start = v.start;
+
+    // Drop the last base while it is shared, extending alleles to the left
+    // with the reference base when one becomes empty.
+    loop {
+        if alleles.iter().any(|x| x.is_empty()) {
+            if start <= 1 {
+                break;
+            }
+            start -= 1;
+            let pos = start as u64 - 1;
+            let base = fasta
+                .fetch(&v.reference_name, pos, pos)
+                .and_then(|x| x.first().copied())
+                .ok_or_else(|| Error::UnknownContig(v.reference_name.clone()))?
+                .to_ascii_uppercase();
+            for allele in &mut alleles {
+                allele.insert(0, base);
+            }
+        } else if alleles.windows(2).all(|w| w[0].last() == w[1].last()) {
+            for allele in &mut alleles {
+                allele.pop();
+            }
+        } else {
+            break;
+        }
+    }
+
+    // Drop the first base while it is shared, keeping at least one base.
+    while alleles.iter().all(|x| x.len() >= 2) && alleles.windows(2).all(|w| w[0][0] == w[1][0]) {
+        for allele in &mut alleles {
+            allele.remove(0);
+        }
+        start += 1;
+    }
+
+    let mut alleles = alleles
+        .into_iter()
+        .map(|x| String::from_utf8(x).expect("alleles are ASCII"));
+    v.reference_bases = alleles.next().unwrap_or_default();
+    v.alternate_bases = alleles.collect();
+    v.start = start;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// Contig 1 is ACGTACGTTTTTGCA, with a run of T from 8 to 12. The
+    /// directory holds the FASTA file and its index.
+    fn fasta() -> (TempDir, Fasta) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ref.fa");
+        fs::write(&path, ">1\nACGTACGTTTTTGCA\n").unwrap();
+        let fasta = Fasta::open(&path).unwrap();
+        (dir, fasta)
+    }
+
+    fn variant(start: i64, reference: &str, alternate: &[&str]) -> Variant {
+        let distribution = json!({
+            "min": 0.0, "q25": 0.0, "median": 0.0, "q75": 0.0, "max": 0.0, "mean": 0.0, "sd": 0.0,
+        });
+        serde_json::from_value(json!({
+            "datasetId": "test",
+            "assemblyId": "hg38",
+            "totalSamples": 0,
+            "referenceName": "1",
+            "start": start,
+            "referenceBases": reference,
+            "alternateBases": alternate,
+            "alleleFrequency": [],
+            "coverage": distribution,
+            "genotypeQuality": distribution,
+        }))
+        .unwrap()
+    }
+
+    fn normalized(fasta: &Fasta, mut v: Variant) -> (i64, String, Vec<String>) {
+        normalize(&mut v, fasta).unwrap();
+        (v.start, v.reference_bases, v.alternate_bases)
+    }
+
+    #[test]
+    fn deletion_is_left_aligned() {
+        let (_dir, fasta) = fasta();
+        let v = normalized(&fasta, variant(11, "TT", &["T"]));
+        assert_eq!(v, (7, "GT".to_string(), vec!["G".to_string()]));
+    }
+
+    #[test]
+    fn shared_bases_are_trimmed() {
+        let (_dir, fasta) = fasta();
+        let v = normalized(&fasta, variant(2, "CA", &["TA"]));
+        assert_eq!(v, (2, "C".to_string(), vec!["T".to_string()]));
+        let v = normalized(&fasta, variant(5, "ACG", &["ATG"]));
+        assert_eq!(v, (6, "C".to_string(), vec!["T".to_string()]));
+    }
+
+    #[test]
+    fn records_without_alt_or_with_symbolic_alleles_are_left_alone() {
+        let (_dir, fasta) = fasta();
+        let v = normalized(&fasta, variant(12, "T", &[]));
+        assert_eq!(v, (12, "T".to_string(), vec![]));
+        let v = normalized(&fasta, variant(8, "TT", &["<DEL>"]));
+        assert_eq!(v, (8, "TT".to_string(), vec!["<DEL>".to_string()]));
+    }
+
+    #[test]
+    fn records_with_alt_equal_to_ref_are_left_alone() {
+        let (_dir, fasta) = fasta();
+        let v = normalized(&fasta, variant(12, "TG", &["tg"]));
+        assert_eq!(v, (12, "TG".to_string(), vec!["tg".to_string()]));
+    }
+}
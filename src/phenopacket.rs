@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Just enough of a GA4GH Phenopacket to describe a cohort.
+#[derive(Deserialize)]
+struct Phenopacket {
+    #[serde(default)]
+    subject: Subject,
+    #[serde(default)]
+    diseases: Vec<Disease>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Subject {
+    sex: Option<String>,
+    time_at_last_encounter: Option<TimeElement>,
+    /// Phenopackets v1.
+    age_at_collection: Option<Age>,
+}
+
+#[derive(Deserialize)]
+struct TimeElement {
+    age: Option<Age>,
+}
+
+#[derive(Deserialize)]
+struct Age {
+    iso8601duration: String,
+}
+
+#[derive(Deserialize)]
+struct Disease {
+    term: OntologyClass,
+    #[serde(default)]
+    excluded: bool,
+}
+
+#[derive(Deserialize)]
+struct OntologyClass {
+    id: String,
+    label: Option<String>,
+}
+
+/// GA4GH Cohort, whose members are phenopackets.
+#[derive(Deserialize)]
+struct CohortFile {
+    members: Vec<Phenopacket>,
+}
+
+/// Number of individuals by sex, age bin and diagnosis.
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Cohort {
+    pub individuals: u32,
+    pub sex: BTreeMap<String, u32>,
+    /// Age at the last encounter in bins of ten years, like `40-49`.
+    pub age_bins: BTreeMap<String, u32>,
+    /// Diagnoses keyed by ontology term, like `OMIM:164400 Spinocerebellar
+    /// ataxia 1`.
+    pub diagnoses: BTreeMap<String, u32>,
+}
+
+impl Cohort {
+    /// Read a directory of phenopacket JSON files, or a cohort JSON file.
+    pub fn load(path: &Path) -> Self {
+        let read = |path: &Path| {
+            fs::read_to_string(path).unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e))
+        };
+        let phenopackets: Vec<Phenopacket> = if path.is_dir() {
+            let mut paths: Vec<_> = fs::read_dir(path)
+                .unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e))
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| path.extension().is_some_and(|x| x == "json"))
+                .collect();
+            paths.sort();
+            paths
+                .iter()
+                .map(|path| {
+                    serde_json::from_str(&read(path))
+                        .unwrap_or_else(|e| panic!("Invalid phenopacket {:?}: {}", path, e))
+                })
+                .collect()
+        } else {
+            let cohort: CohortFile = serde_json::from_str(&read(path))
+                .unwrap_or_else(|e| panic!("Invalid cohort {:?}: {}", path, e));
+            cohort.members
+        };
+
+        let mut cohort = Cohort::default();
+        for phenopacket in &phenopackets {
+            cohort.add(phenopacket);
+        }
+        cohort
+    }
+
+    fn add(&mut self, phenopacket: &Phenopacket) {
+        self.individuals += 1;
+        let subject = &phenopacket.subject;
+        let sex = subject.sex.as_deref().unwrap_or("UNKNOWN_SEX");
+        *self.sex.entry(sex.to_string()).or_default() += 1;
+
+        let age = subject
+            .time_at_last_encounter
+            .as_ref()
+            .and_then(|x| x.age.as_ref())
+            .or(subject.age_at_collection.as_ref())
+            .and_then(|x| years(&x.iso8601duration));
+        let bin = match age {
+            Some(age) => format!("{}-{}", age / 10 * 10, age / 10 * 10 + 9),
+            None => "unknown".to_string(),
+        };
+        *self.age_bins.entry(bin).or_default() += 1;
+
+        for disease in phenopacket.diseases.iter().filter(|x| !x.excluded) {
+            let term = match &disease.term.label {
+                Some(label) => format!("{} {}", disease.term.id, label),
+                None => disease.term.id.clone(),
+            };
+            *self.diagnoses.entry(term).or_default() += 1;
+        }
+    }
+}
+
+/// Whole years of an ISO 8601 duration such as `P45Y6M`.
+fn years(duration: &str) -> Option<u32> {
+    let duration = duration.strip_prefix('P')?;
+    match duration.split_once('Y') {
+        Some((years, _)) => years.parse().ok(),
+        None if duration.contains('M') || duration.contains('W') || duration.contains('D') => {
+            Some(0)
+        }
+        None => None,
+    }
+}
@@ -0,0 +1,123 @@
+use crate::error::Result;
+use crate::variant::{get_info_field, has_info, subfield_names};
+use rust_htslib::bcf::header::HeaderView;
+use rust_htslib::bcf::Record;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Categorical call and score of a predictor such as SIFT or PolyPhen.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct Prediction {
+    pub prediction: String,
+    pub score: Option<f64>,
+}
+
+impl Prediction {
+    /// Parse VEP values such as `deleterious(0.01)`.
+    fn parse(value: &str) -> Option<Self> {
+        let (prediction, score) = match value.split_once('(') {
+            Some((prediction, score)) => (prediction, score.trim_end_matches(')').parse().ok()),
+            None => (value, None),
+        };
+        if prediction.is_empty() {
+            return None;
+        }
+        Some(Prediction {
+            prediction: prediction.to_string(),
+            score,
+        })
+    }
+}
+
+/// Positions of SIFT and PolyPhen in VEP CSQ entries, from the header.
+#[derive(Clone, Copy, Default)]
+pub struct CsqFields {
+    sift: Option<usize>,
+    polyphen: Option<usize>,
+}
+
+impl CsqFields {
+    pub fn from_header(header: &HeaderView) -> Self {
+        let names = subfield_names(header, "CSQ").unwrap_or_default();
+        CsqFields {
+            sift: names.iter().position(|x| x == "sift"),
+            polyphen: names.iter().position(|x| x == "polyphen"),
+        }
+    }
+}
+
+/// First prediction found in the CSQ entries of a record.
+fn from_csq(entries: &[String], index: Option<usize>) -> Option<Prediction> {
+    let index = index?;
+    entries
+        .iter()
+        .find_map(|entry| Prediction::parse(entry.split('|').nth(index)?))
+}
+
+/// First prediction of dbNSFP fields added by SnpSift, with one-letter
+/// calls such as `D` for SIFT deleterious.
+fn from_dbnsfp(
+    record: &Record,
+    pred_tag: &str,
+    score_tag: &str,
+    names: &[(&str, &str)],
+) -> Result<Option<Prediction>> {
+    if !has_info(record, pred_tag) {
+        return Ok(None);
+    }
+    let first = |tag: &str| -> Result<Option<String>> {
+        if !has_info(record, tag) {
+            return Ok(None);
+        }
+        Ok(get_info_field(record, tag)?
+            .unwrap_or_default()
+            .into_iter()
+            .find(|x| x != "."))
+    };
+    let prediction = match first(pred_tag)? {
+        Some(call) => names
+            .iter()
+            .find(|(code, _)| *code == call)
+            .map_or(call.clone(), |(_, name)| name.to_string()),
+        None => return Ok(None),
+    };
+    Ok(Some(Prediction {
+        prediction,
+        score: first(score_tag)?.and_then(|x| x.parse().ok()),
+    }))
+}
+
+/// SIFT and PolyPhen predictions of a record, from VEP CSQ or dbNSFP.
+pub fn predictions(
+    record: &Record,
+    csq: &CsqFields,
+) -> Result<(Option<Prediction>, Option<Prediction>)> {
+    let entries = if has_info(record, "CSQ") {
+        get_info_field(record, "CSQ")?.unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let sift = match from_csq(&entries, csq.sift) {
+        Some(sift) => Some(sift),
+        None => from_dbnsfp(
+            record,
+            "dbNSFP_SIFT_pred",
+            "dbNSFP_SIFT_score",
+            &[("D", "deleterious"), ("T", "tolerated")],
+        )?,
+    };
+    let polyphen = match from_csq(&entries, csq.polyphen) {
+        Some(polyphen) => Some(polyphen),
+        None => from_dbnsfp(
+            record,
+            "dbNSFP_Polyphen2_HDIV_pred",
+            "dbNSFP_Polyphen2_HDIV_score",
+            &[
+                ("D", "probably_damaging"),
+                ("P", "possibly_damaging"),
+                ("B", "benign"),
+            ],
+        )?,
+    };
+    Ok((sift, polyphen))
+}
@@ -0,0 +1,116 @@
+use crate::cli::ServerOpts;
+use crate::exit::Exit;
+use log::{debug, info};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// Oldest and newest major API versions this importer can talk to.
+const SUPPORTED: (u32, u32) = (1, 2);
+
+/// Version of the BraVE server API, from its `/info` endpoint.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct ApiVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl ApiVersion {
+    /// The `/variants/batch` endpoint was added in API 2.
+    pub fn supports_batch(&self) -> bool {
+        self.major >= 2
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Parse versions written as `2`, `2.1` or `2.1.0`.
+impl FromStr for ApiVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim_start_matches('v').split('.');
+        let mut next = || -> Result<u32, String> {
+            match parts.next() {
+                Some(x) => x.parse().map_err(|_| format!("invalid API version {}", s)),
+                None => Ok(0),
+            }
+        };
+        Ok(ApiVersion {
+            major: next()?,
+            minor: next()?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Info {
+    api_version: String,
+}
+
+/// Check that the server is reachable, healthy and accepts the credentials
+/// before reading any VCF file, so a typo in `--host` or a wrong password is
+/// reported right away. Returns the API version of the server, if it tells.
+pub fn check(server: &ServerOpts) -> Option<ApiVersion> {
+    let url = format!("{}/health", server.host());
+    let res = server
+        .auth()
+        .apply(server.client().get(url))
+        .send()
+        .unwrap_or_else(|e| {
+            Exit::Unreachable.fail(format!("Can't reach server at {}: {}", server.host(), e))
+        });
+
+    match res.status() {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Exit::AuthFailed.fail(format!(
+            "Server at {} rejected credentials: {}",
+            server.host(),
+            res.status()
+        )),
+        // Older servers don't have a health endpoint, but still answer.
+        StatusCode::NOT_FOUND => debug!("Server at {} has no health endpoint", server.host()),
+        status if status.is_success() => info!("Connected to server at {}", server.host()),
+        status => Exit::Unreachable.fail(format!(
+            "Server at {} is not healthy: {}",
+            server.host(),
+            status
+        )),
+    }
+
+    let version = api_version(server)?;
+    let (oldest, newest) = SUPPORTED;
+    if version.major < oldest || version.major > newest {
+        Exit::Unreachable.fail(format!(
+            "Server at {} has API version {}, but only versions {}.x to {}.x are supported",
+            server.host(),
+            version,
+            oldest,
+            newest
+        ));
+    }
+    info!("Server API version {}", version);
+    Some(version)
+}
+
+fn api_version(server: &ServerOpts) -> Option<ApiVersion> {
+    let url = format!("{}/info", server.host());
+    let res = server.auth().apply(server.client().get(&url)).send().ok()?;
+    if !res.status().is_success() {
+        debug!("Server at {} has no info endpoint", server.host());
+        return None;
+    }
+    let info: Info = res
+        .json()
+        .unwrap_or_else(|e| panic!("Invalid response from {}: {}", url, e));
+    let version = info
+        .api_version
+        .parse()
+        .unwrap_or_else(|e| panic!("Invalid response from {}: {}", url, e));
+    Some(version)
+}
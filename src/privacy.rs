@@ -0,0 +1,203 @@
+use crate::variant::{GenotypeCounts, Variant};
+use clap::ArgEnum;
+use rand::Rng;
+
+/// How allele frequencies are blurred before they are published.
+#[derive(ArgEnum, Clone, Copy, PartialEq, Debug)]
+pub enum PrivacyMode {
+    /// Round to --af-decimals.
+    Round,
+    /// Add Laplace noise calibrated with --epsilon, then round.
+    Noise,
+}
+
+pub struct Privacy {
+    pub mode: PrivacyMode,
+    pub decimals: i32,
+    pub epsilon: f64,
+    /// Counts from one up to this, excluded, are left out of the payload.
+    pub min_count: u32,
+}
+
+impl Privacy {
+    /// Blur allele and genotype counts, then derive the allele frequencies
+    /// from the blurred counts, so AC/AN can't give the exact frequency
+    /// back.
+    pub fn apply(&self, v: &mut Variant) {
+        self.suppress(v);
+
+        let an = v.allele_number.unwrap_or(2 * v.total_samples).max(1);
+        match &mut v.allele_count {
+            Some(ac) => {
+                for x in ac.iter_mut() {
+                    *x = self.blur(*x, an);
+                }
+                v.allele_frequency = ac
+                    .iter()
+                    .map(|&x| self.round(f64::from(x) / f64::from(an)) as f32)
+                    .collect();
+            }
+            // Without counts, the frequencies are blurred instead, with
+            // the noise of a count divided by AN.
+            None => {
+                for af in &mut v.allele_frequency {
+                    let x = self.round(self.noise(f64::from(*af), f64::from(an)));
+                    *af = x as f32;
+                }
+            }
+        }
+
+        if let Some(gc) = &mut v.genotype_counts {
+            for counts in gc.iter_mut() {
+                let samples = counts.hom_ref + counts.het + counts.hom_alt;
+                counts.hom_ref = self.blur(counts.hom_ref, samples);
+                counts.het = self.blur(counts.het, samples);
+                counts.hom_alt = self.blur(counts.hom_alt, samples);
+                counts.set_hwe();
+            }
+        }
+    }
+
+    /// A count out of total, with Laplace noise in noise mode, as the
+    /// count of the frequency rounded to --af-decimals.
+    fn blur(&self, count: u32, total: u32) -> u32 {
+        if total == 0 {
+            return count;
+        }
+        let total = f64::from(total);
+        let x = self.noise(f64::from(count) / total, total);
+        (self.round(x) * total).round() as u32
+    }
+
+    /// Frequency with Laplace noise of a count out of total in noise mode.
+    fn noise(&self, x: f64, total: f64) -> f64 {
+        match self.mode {
+            PrivacyMode::Round => x,
+            // One sample changes a count by at most two, e.g. both alleles
+            // of a diploid site.
+            PrivacyMode::Noise => (x + laplace(2.0 / (total * self.epsilon))).clamp(0.0, 1.0),
+        }
+    }
+
+    fn round(&self, x: f64) -> f64 {
+        let factor = 10f64.powi(self.decimals);
+        (x * factor).round() / factor
+    }
+
+    /// Leave out counts from one up to min_count, excluded. Low allele
+    /// counts take the frequencies and allele number with them, as AF × AN
+    /// gives them back.
+    fn suppress(&self, v: &mut Variant) {
+        let low = |x: u32| (1..self.min_count).contains(&x);
+        let an = f64::from(v.allele_number.unwrap_or(2 * v.total_samples));
+        let low_alleles = match &v.allele_count {
+            Some(ac) => ac.iter().any(|&x| low(x)),
+            None => v
+                .allele_frequency
+                .iter()
+                .any(|&af| low((f64::from(af) * an).round() as u32)),
+        };
+        if low_alleles {
+            v.allele_count = None;
+            v.allele_frequency.clear();
+            v.allele_number = None;
+        }
+        let low_genotypes = |gc: &Vec<GenotypeCounts>| {
+            gc.iter()
+                .any(|x| low(x.hom_ref) || low(x.het) || low(x.hom_alt))
+        };
+        if v.genotype_counts.as_ref().is_some_and(low_genotypes) {
+            v.genotype_counts = None;
+        }
+        if v.sample_count.is_some_and(|x| low(x.max(0) as u32)) {
+            v.sample_count = None;
+        }
+    }
+}
+
+/// Sample of a Laplace distribution centered at zero.
+fn laplace(scale: f64) -> f64 {
+    let u: f64 = rand::thread_rng().gen_range(-0.5..0.5);
+    -scale * u.signum() * (1.0 - 2.0 * u.abs()).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn variant() -> Variant {
+        serde_json::from_value(json!({
+            "datasetId": "test",
+            "assemblyId": "hg38",
+            "totalSamples": 50,
+            "referenceName": "1",
+            "start": 100,
+            "referenceBases": "A",
+            "alternateBases": ["G"],
+            "alleleFrequency": [0.37],
+            "alleleCount": [37],
+            "alleleNumber": 100,
+            "genotypeCounts": [{"homRef": 31, "het": 6, "homAlt": 13, "hwePValue": 0.0}],
+        }))
+        .unwrap()
+    }
+
+    fn privacy(mode: PrivacyMode, min_count: u32) -> Privacy {
+        Privacy {
+            mode,
+            decimals: 1,
+            epsilon: 1.0,
+            min_count,
+        }
+    }
+
+    #[test]
+    fn round_blurs_counts_and_frequencies() {
+        let mut v = variant();
+        privacy(PrivacyMode::Round, 0).apply(&mut v);
+        assert_eq!(v.allele_count, Some(vec![40]));
+        assert_eq!(v.allele_frequency, vec![0.4]);
+        let gc = &v.genotype_counts.unwrap()[0];
+        assert_eq!((gc.hom_ref, gc.het, gc.hom_alt), (30, 5, 15));
+    }
+
+    #[test]
+    fn noisy_frequencies_match_noisy_counts() {
+        for _ in 0..100 {
+            let mut v = variant();
+            privacy(PrivacyMode::Noise, 0).apply(&mut v);
+            let ac = v.allele_count.unwrap()[0];
+            assert!(ac <= 100);
+            let af = (f64::from(ac) / 100.0 * 10.0).round() / 10.0;
+            assert_eq!(v.allele_frequency, vec![af as f32]);
+        }
+    }
+
+    #[test]
+    fn low_counts_are_left_out() {
+        let mut v = variant();
+        privacy(PrivacyMode::Round, 10).apply(&mut v);
+        assert_eq!(v.allele_count, Some(vec![40]));
+        assert_eq!(v.allele_frequency, vec![0.4]);
+        assert!(v.genotype_counts.is_none());
+
+        let mut v = variant();
+        v.allele_count = Some(vec![3]);
+        v.allele_frequency = vec![0.03];
+        privacy(PrivacyMode::Round, 10).apply(&mut v);
+        assert!(v.allele_count.is_none());
+        assert!(v.allele_frequency.is_empty());
+        assert!(v.allele_number.is_none());
+    }
+
+    #[test]
+    fn low_frequencies_without_counts_are_left_out() {
+        let mut v = variant();
+        v.allele_count = None;
+        v.allele_frequency = vec![0.03];
+        privacy(PrivacyMode::Noise, 10).apply(&mut v);
+        assert!(v.allele_frequency.is_empty());
+        assert!(v.allele_number.is_none());
+    }
+}
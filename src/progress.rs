@@ -0,0 +1,111 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Progress bar on stderr showing records read, with an ETA when the number
+/// of records is known from the index, and uploaded and failed variants.
+/// Clones update the same bar, so readers and upload workers can share it.
+#[derive(Clone)]
+pub struct Progress {
+    bar: ProgressBar,
+    uploaded: Arc<AtomicU64>,
+    failed: Arc<AtomicU64>,
+    responses: Arc<Mutex<BTreeMap<u16, u64>>>,
+    contigs: Arc<Mutex<BTreeMap<String, u64>>>,
+}
+
+impl Progress {
+    pub fn new(total: Option<u64>) -> Self {
+        let bar = match total {
+            Some(total) => ProgressBar::new(total).with_style(
+                ProgressStyle::with_template(
+                    "{elapsed_precise} [{bar:40}] {pos}/{len} records ({per_sec}, ETA {eta}) {msg}",
+                )
+                .unwrap()
+                .progress_chars("=> "),
+            ),
+            None => ProgressBar::new_spinner().with_style(
+                ProgressStyle::with_template(
+                    "{spinner} {elapsed_precise} {pos} records ({per_sec}) {msg}",
+                )
+                .unwrap(),
+            ),
+        };
+        bar.enable_steady_tick(Duration::from_millis(200));
+        Progress::with_bar(bar)
+    }
+
+    pub fn hidden() -> Self {
+        Progress::with_bar(ProgressBar::hidden())
+    }
+
+    fn with_bar(bar: ProgressBar) -> Self {
+        Progress {
+            bar,
+            uploaded: Arc::new(AtomicU64::new(0)),
+            failed: Arc::new(AtomicU64::new(0)),
+            responses: Default::default(),
+            contigs: Default::default(),
+        }
+    }
+
+    /// Count a record read from the VCF file, whether it is imported or not.
+    pub fn read(&self) {
+        self.bar.inc(1);
+    }
+
+    pub fn uploaded(&self, variants: u64) {
+        self.uploaded.fetch_add(variants, Ordering::Relaxed);
+        self.update();
+    }
+
+    pub fn failed(&self) {
+        self.failed.fetch_add(1, Ordering::Relaxed);
+        self.update();
+    }
+
+    /// Count uploaded variants by contig of the VCF file.
+    pub fn uploaded_contigs(&self, contigs: &BTreeMap<String, u64>) {
+        let mut uploaded = self.contigs.lock().unwrap();
+        for (contig, n) in contigs {
+            *uploaded.entry(contig.clone()).or_default() += n;
+        }
+    }
+
+    pub fn contigs(&self) -> BTreeMap<String, u64> {
+        self.contigs.lock().unwrap().clone()
+    }
+
+    /// Count a response of the server by status code.
+    pub fn response(&self, status: u16) {
+        *self.responses.lock().unwrap().entry(status).or_default() += 1;
+    }
+
+    pub fn uploaded_variants(&self) -> u64 {
+        self.uploaded.load(Ordering::Relaxed)
+    }
+
+    pub fn failed_requests(&self) -> u64 {
+        self.failed.load(Ordering::Relaxed)
+    }
+
+    pub fn responses(&self) -> BTreeMap<u16, u64> {
+        self.responses.lock().unwrap().clone()
+    }
+
+    fn update(&self) {
+        let uploaded = self.uploaded.load(Ordering::Relaxed);
+        let failed = self.failed.load(Ordering::Relaxed);
+        let rate = uploaded as f64 / self.bar.elapsed().as_secs_f64().max(1.0);
+        self.bar.set_message(format!(
+            "uploaded {} ({:.0}/s), failed requests {}",
+            uploaded, rate, failed
+        ));
+    }
+
+    pub fn finish(&self) {
+        self.bar.finish_and_clear();
+    }
+}
@@ -0,0 +1,57 @@
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// How sample IDs are written to logs and any output, so that raw hospital
+/// IDs never leave the site.
+#[derive(Clone, Default)]
+pub enum Pseudonyms {
+    /// Sample IDs as in the VCF file.
+    #[default]
+    Raw,
+    /// First 16 hex digits of the SHA-256 of the salt and the sample ID.
+    Salted(String),
+    /// Pseudonyms read from a file of tab-separated sample IDs and
+    /// pseudonyms.
+    Map(HashMap<String, String>),
+}
+
+impl Pseudonyms {
+    pub fn load_map(path: &Path) -> Self {
+        let content =
+            fs::read_to_string(path).unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e));
+        let map = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+            .map(|(i, line)| match line.split_once('\t') {
+                Some((sample, pseudonym)) => {
+                    (sample.trim().to_string(), pseudonym.trim().to_string())
+                }
+                None => panic!("Invalid pseudonym map {:?} at line {}", path, i + 1),
+            })
+            .collect();
+        Pseudonyms::Map(map)
+    }
+
+    /// Name to show for a sample. Samples missing from a map get a
+    /// placeholder rather than their ID.
+    pub fn name<'a>(&self, sample: &'a str) -> Cow<'a, str> {
+        match self {
+            Pseudonyms::Raw => Cow::Borrowed(sample),
+            Pseudonyms::Salted(salt) => {
+                let mut hasher = Sha256::new();
+                hasher.update(salt.as_bytes());
+                hasher.update(sample.as_bytes());
+                let digest = hasher.finalize();
+                Cow::Owned(digest[..8].iter().map(|x| format!("{:02x}", x)).collect())
+            }
+            Pseudonyms::Map(map) => match map.get(sample) {
+                Some(pseudonym) => Cow::Owned(pseudonym.clone()),
+                None => Cow::Borrowed("<unmapped sample>"),
+            },
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use crate::cli::{OutputFormat, QueryOpts};
+use crate::ndjson;
+use crate::remote::{variant_query, VariantPages};
+use crate::variant::Variant;
+use log::info;
+use std::io::Write;
+
+const TSV_HEADER: &str =
+    "referenceName\tstart\treferenceBases\talternateBases\talleleFrequency\tsampleCount\tgeneSymbol\thgvs\ttype\tclnsig\tsnpIds";
+
+/// Join list values with commas, writing `.` for missing ones like VCF.
+fn join<T: ToString>(values: Option<&[T]>) -> String {
+    match values {
+        Some(values) if !values.is_empty() => values
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+            .join(","),
+        _ => ".".to_string(),
+    }
+}
+
+fn tsv_row(v: &Variant) -> String {
+    [
+        v.reference_name.clone(),
+        v.start.to_string(),
+        v.reference_bases.clone(),
+        join(Some(&v.alternate_bases)),
+        join(Some(&v.allele_frequency)),
+        v.sample_count.map_or(".".to_string(), |x| x.to_string()),
+        join(v.gene_symbol.as_deref()),
+        join(v.hgvs.as_deref()),
+        join(v.variant_type.as_deref()),
+        v.clnsig.clone().unwrap_or_else(|| ".".to_string()),
+        join(v.snp_ids.as_deref()),
+    ]
+    .join("\t")
+}
+
+pub fn run(opts: QueryOpts) {
+    let query = variant_query(
+        &opts.dataset,
+        opts.assembly.as_deref(),
+        opts.region.as_ref(),
+    );
+
+    let mut out = ndjson::create(&opts.output)
+        .unwrap_or_else(|e| panic!("Error creating {:?}: {}", opts.output, e));
+    if opts.format == OutputFormat::Tsv {
+        writeln!(out, "{}", TSV_HEADER).unwrap();
+    }
+
+    let mut total = 0;
+    for v in VariantPages::new(&opts.server, query) {
+        match opts.format {
+            OutputFormat::Json => {
+                ndjson::write_line(&mut out, &serde_json::to_vec(&v).unwrap()).unwrap()
+            }
+            OutputFormat::Tsv => writeln!(out, "{}", tsv_row(&v)).unwrap(),
+        }
+        total += 1;
+    }
+    out.flush().unwrap();
+
+    info!("Fetched {} variants", total);
+}
@@ -0,0 +1,854 @@
+use crate::annotation::AnnotationFile;
+use crate::checkpoint::Position;
+use crate::cli::{InputOpts, StarAlleles};
+use crate::clinvar;
+use crate::consequence::ConsequenceFilter;
+use crate::contig::ContigNames;
+use crate::error;
+use crate::exit::Exit;
+use crate::fasta::Fasta;
+use crate::gnomad;
+use crate::htsget;
+use crate::liftover::Chain;
+use crate::normalize::normalize;
+use crate::prediction::CsqFields;
+use crate::privacy::Privacy;
+use crate::progress::Progress;
+use crate::pseudonym::Pseudonyms;
+use crate::region::{self, Region, RegionSet};
+use crate::report::Report;
+use crate::samples::SampleFilter;
+use crate::script::Script;
+use crate::spdi::Spdi;
+use crate::variant::{ann_entries, get_reference_name, AnnFields, Context, Variant, NON_REF, NS};
+use crate::vrs::Vrs;
+use log::warn;
+use rust_htslib::bcf::header::HeaderView;
+use rust_htslib::bcf::{IndexedReader, Read, Reader, Record};
+use rust_htslib::errors as htslib;
+use rust_htslib::htslib as hts;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::error::Error;
+use std::ffi::CString;
+use std::fmt;
+use url::Url;
+
+/// Records read, passing the filters, and that can't be converted, on a
+/// contig.
+#[derive(Default, Clone)]
+pub struct ContigCounts {
+    pub total: u32,
+    pub passed: u32,
+    pub failed: u32,
+}
+
+#[derive(Default)]
+pub struct Counts {
+    pub files: u32,
+    pub total: u32,
+    pub passed: u32,
+    pub skipped: u32,
+    /// Records skipped by --resume, imported by an earlier run.
+    pub resumed: u32,
+    /// Records failing the FILTER or --bed checks.
+    pub filtered: u32,
+    /// Variants left out by --skip-svs and --skip-bnd.
+    pub structural: u32,
+    /// Variants left out by --genes.
+    pub other_genes: u32,
+    /// Variants left out by --min-call-rate.
+    pub low_call_rate: u32,
+    /// Variants left out by --min-impact and --consequences.
+    pub low_impact: u32,
+    /// gVCF reference blocks left out.
+    pub reference_blocks: u32,
+    /// Records whose spanning deletion (*) allele was removed.
+    pub star_alleles: u32,
+    /// ALT alleles withheld by --min-allele-count and --min-carriers.
+    pub rare_alleles: u32,
+    /// Variants left out by the --script transform function.
+    pub vetoed: u32,
+    /// Skipped records by kind of error.
+    pub errors: BTreeMap<String, u32>,
+    /// Counts by contig of the VCF file.
+    pub contigs: BTreeMap<String, ContigCounts>,
+}
+
+impl Counts {
+    /// Add counts of another reader that read other regions of the same
+    /// files.
+    pub fn merge(&mut self, other: &Counts) {
+        self.files = self.files.max(other.files);
+        self.total += other.total;
+        self.passed += other.passed;
+        self.skipped += other.skipped;
+        self.resumed += other.resumed;
+        self.filtered += other.filtered;
+        self.structural += other.structural;
+        self.other_genes += other.other_genes;
+        self.star_alleles += other.star_alleles;
+        self.rare_alleles += other.rare_alleles;
+        self.reference_blocks += other.reference_blocks;
+        self.low_call_rate += other.low_call_rate;
+        self.low_impact += other.low_impact;
+        self.vetoed += other.vetoed;
+        for (kind, n) in &other.errors {
+            *self.errors.entry(kind.clone()).or_default() += n;
+        }
+        for (contig, counts) in &other.contigs {
+            let total = self.contigs.entry(contig.clone()).or_default();
+            total.total += counts.total;
+            total.passed += counts.passed;
+            total.failed += counts.failed;
+        }
+    }
+
+    pub fn summary(&self, do_filter: bool) -> String {
+        let mut summary = String::new();
+        if self.files > 1 {
+            summary += &format!("Files: {}\n", self.files);
+        }
+        summary += &format!("Total variants: {}\n", self.total);
+        if do_filter {
+            summary += &format!("Passed variants: {}\n", self.passed);
+        }
+        summary += &format!("Skipped variants: {}", self.skipped);
+        if self.resumed > 0 {
+            summary += &format!("\nRecords skipped by --resume: {}", self.resumed);
+        }
+        if self.structural > 0 {
+            summary += &format!("\nSkipped structural variants: {}", self.structural);
+        }
+        if self.other_genes > 0 {
+            summary += &format!("\nVariants in other genes: {}", self.other_genes);
+        }
+        if self.low_impact > 0 {
+            summary += &format!("\nLow impact variants: {}", self.low_impact);
+        }
+        if self.vetoed > 0 {
+            summary += &format!("\nVariants left out by script: {}", self.vetoed);
+        }
+        if self.rare_alleles > 0 {
+            summary += &format!("\nWithheld rare alleles: {}", self.rare_alleles);
+        }
+        if self.low_call_rate > 0 {
+            summary += &format!("\nLow call rate variants: {}", self.low_call_rate);
+        }
+        if self.reference_blocks > 0 {
+            summary += &format!("\ngVCF reference blocks: {}", self.reference_blocks);
+        }
+        if self.star_alleles > 0 {
+            summary += &format!("\nRemoved * alleles: {}", self.star_alleles);
+        }
+        summary
+    }
+
+    fn contig(&mut self, name: String) -> &mut ContigCounts {
+        self.contigs.entry(name).or_default()
+    }
+}
+
+/// Names of the contigs declared in the header of a VCF file.
+pub fn header_contigs(path: &str) -> htslib::Result<Vec<String>> {
+    let bcf = match remote_url(path) {
+        Some(url) => Reader::from_url(&url)?,
+        None => Reader::from_path(path)?,
+    };
+    let header = bcf.header();
+    (0..header.contig_count())
+        .map(|rid| Ok(String::from_utf8_lossy(header.rid2name(rid)?).to_string()))
+        .collect()
+}
+
+/// Number of records of an indexed VCF file, from the per-contig counts
+/// kept by CSI and tabix indexes. None if the file has no index.
+pub fn indexed_record_count(path: &str) -> Option<u64> {
+    let contigs = Reader::from_path(path).ok()?.header().contig_count();
+    let path = CString::new(path).ok()?;
+
+    // SAFETY: idx is checked for null and destroyed once, after its last use.
+    unsafe {
+        let idx = hts::hts_idx_load3(
+            path.as_ptr(),
+            std::ptr::null(),
+            hts::HTS_FMT_TBI as i32,
+            hts::HTS_IDX_SILENT_FAIL as i32,
+        );
+        if idx.is_null() {
+            return None;
+        }
+        let mut total = hts::hts_idx_get_n_no_coor(idx);
+        for tid in 0..contigs {
+            let (mut mapped, mut unmapped) = (0, 0);
+            if hts::hts_idx_get_stat(idx, tid as i32, &mut mapped, &mut unmapped) >= 0 {
+                total += mapped + unmapped;
+            }
+        }
+        hts::hts_idx_destroy(idx);
+        Some(total).filter(|&total| total > 0)
+    }
+}
+
+/// VCF file given as argument, or its slice served by an htsget server.
+enum Input {
+    File(String),
+    Htsget {
+        endpoint: String,
+        region: Option<Region>,
+    },
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Input::File(path) => write!(f, "{}", path),
+            Input::Htsget {
+                endpoint,
+                region: Some(region),
+            } => write!(f, "{} ({})", endpoint, region),
+            Input::Htsget { endpoint, .. } => write!(f, "{}", endpoint),
+        }
+    }
+}
+
+/// Either the whole VCF file or only some regions of it, using its index.
+enum Source {
+    Stream(Reader),
+    /// Records downloaded for a region, which may include records that
+    /// overlap the region but start before it.
+    Slice {
+        bcf: Reader,
+        region: Region,
+    },
+    Indexed {
+        bcf: IndexedReader,
+        regions: VecDeque<Region>,
+        current: Option<Region>,
+    },
+}
+
+impl Source {
+    /// Open path, "-" meaning stdin which can't be read by regions.
+    fn open(path: &str, regions: Vec<Region>) -> htslib::Result<Source> {
+        if path == "-" {
+            assert!(regions.is_empty(), "Regions can't be read from stdin");
+            return Ok(Source::Stream(Reader::from_stdin()?));
+        }
+
+        let url = remote_url(path);
+
+        if regions.is_empty() {
+            return match url {
+                Some(url) => Ok(Source::Stream(Reader::from_url(&url)?)),
+                None => Ok(Source::Stream(Reader::from_path(path)?)),
+            };
+        }
+
+        let bcf = match url {
+            Some(url) => IndexedReader::from_url(&url)?,
+            None => IndexedReader::from_path(path)?,
+        };
+        let header = bcf.header();
+        let regions = region::merge(regions, |contig| header.name2rid(contig.as_bytes()).ok());
+        Ok(Source::Indexed {
+            bcf,
+            regions: regions.into(),
+            current: None,
+        })
+    }
+
+    /// Download region, or the whole VCF, from an htsget endpoint.
+    fn download(endpoint: &str, region: Option<Region>) -> Result<Source, Box<dyn Error>> {
+        let path = htsget::download(endpoint, region.as_ref())?;
+        let bcf = Reader::from_path(&path)?;
+        // htslib keeps the file open, so it can be removed right away
+        path.close()?;
+        Ok(match region {
+            Some(region) => Source::Slice { bcf, region },
+            None => Source::Stream(bcf),
+        })
+    }
+
+    fn header(&self) -> &HeaderView {
+        match self {
+            Source::Stream(bcf) => bcf.header(),
+            Source::Slice { bcf, .. } => bcf.header(),
+            Source::Indexed { bcf, .. } => bcf.header(),
+        }
+    }
+
+    fn set_threads(&mut self, n_threads: usize) -> htslib::Result<()> {
+        match self {
+            Source::Stream(bcf) => bcf.set_threads(n_threads),
+            Source::Slice { bcf, .. } => bcf.set_threads(n_threads),
+            Source::Indexed { bcf, .. } => bcf.set_threads(n_threads),
+        }
+    }
+
+    fn empty_record(&self) -> Record {
+        match self {
+            Source::Stream(bcf) => bcf.empty_record(),
+            Source::Slice { bcf, .. } => bcf.empty_record(),
+            Source::Indexed { bcf, .. } => bcf.empty_record(),
+        }
+    }
+
+    fn read(&mut self, record: &mut Record) -> Option<htslib::Result<()>> {
+        let (bcf, regions, current) = match self {
+            Source::Stream(bcf) => return bcf.read(record),
+            Source::Slice { bcf, region } => loop {
+                match bcf.read(record) {
+                    Some(Ok(())) if !region.contains(record.pos() as u64) => continue,
+                    result => return result,
+                }
+            },
+            Source::Indexed {
+                bcf,
+                regions,
+                current,
+            } => (bcf, regions, current),
+        };
+
+        loop {
+            if let Some(region) = current {
+                match bcf.read(record) {
+                    // Records starting before the region but overlapping it
+                    // belong to the previous region or weren't requested.
+                    Some(Ok(())) if !region.contains(record.pos() as u64) => continue,
+                    Some(result) => return Some(result),
+                    None => {}
+                }
+            }
+
+            let region = regions.pop_front()?;
+            match bcf.header().name2rid(region.contig.as_bytes()) {
+                Ok(rid) => {
+                    if let Err(e) = bcf.fetch(rid, region.start, region.end) {
+                        return Some(Err(e));
+                    }
+                    *current = Some(region);
+                }
+                Err(_) => {
+                    warn!("Contig {} not found in VCF header", region.contig);
+                    *current = None;
+                }
+            }
+        }
+    }
+}
+
+/// Parse path as URL if it is one htslib can read remotely.
+pub fn remote_url(path: &str) -> Option<Url> {
+    let url = Url::parse(path).ok()?;
+    match url.scheme() {
+        "http" | "https" | "ftp" | "s3" | "s3+http" | "s3+https" | "gs" | "gs+http"
+        | "gs+https" => Some(url),
+        _ => None,
+    }
+}
+
+/// Files to read, or one htsget request per region when reading from an
+/// htsget server.
+fn inputs(input: &InputOpts) -> Vec<Input> {
+    if !input.htsget {
+        return input.vcf_files().into_iter().map(Input::File).collect();
+    }
+
+    let regions = region::merge(input.regions(), |_| None);
+    let mut inputs = Vec::new();
+    for endpoint in input.vcf_files() {
+        if regions.is_empty() {
+            inputs.push(Input::Htsget {
+                endpoint,
+                region: None,
+            });
+            continue;
+        }
+        for region in &regions {
+            inputs.push(Input::Htsget {
+                endpoint: endpoint.clone(),
+                region: Some(region.clone()),
+            });
+        }
+    }
+    inputs
+}
+
+/// Reads VCF files one after the other and converts their records to
+/// variants, leaving out records that didn't pass filters or that can't be
+/// converted.
+pub struct VariantReader {
+    inputs: VecDeque<Input>,
+    regions: Vec<Region>,
+    decompress_threads: usize,
+    bcf: Option<Source>,
+    context: Context,
+    do_filter: bool,
+    panel: Option<RegionSet>,
+    genes: Option<HashSet<String>>,
+    reference: Option<Fasta>,
+    normalize: bool,
+    liftover: Option<Chain>,
+    clinvar: Option<AnnotationFile>,
+    gnomad: Option<AnnotationFile>,
+    dbsnp: Option<AnnotationFile>,
+    script: Option<Script>,
+    vrs: Option<Vrs>,
+    spdi: Option<Spdi>,
+    privacy: Option<Privacy>,
+    sample_filter: Option<SampleFilter>,
+    pseudonyms: Pseudonyms,
+    contig_names: ContigNames,
+    resume_from: Option<Position>,
+    split_multiallelics: bool,
+    star_alleles: StarAlleles,
+    skip_svs: bool,
+    skip_bnd: bool,
+    min_call_rate: Option<f64>,
+    min_allele_count: Option<u32>,
+    min_carriers: Option<u32>,
+    consequences: Option<ConsequenceFilter>,
+    /// Variants of a split record not returned yet.
+    pending: VecDeque<(Position, Variant)>,
+    /// Position of the last record whose variants were all returned.
+    last_position: Position,
+    progress: Progress,
+    pub counts: Counts,
+    /// Filled with FILTER values and problems of records when set.
+    pub report: Option<Report>,
+}
+
+impl VariantReader {
+    pub fn new(input: &InputOpts, dataset_id: String, assembly_id: String) -> Self {
+        Self::with_regions(input, dataset_id, assembly_id, input.regions())
+    }
+
+    /// Read only regions instead of the regions selected by input options.
+    pub fn with_regions(
+        input: &InputOpts,
+        dataset_id: String,
+        assembly_id: String,
+        regions: Vec<Region>,
+    ) -> Self {
+        for bins in [&input.coverage_bins, &input.gq_bins] {
+            assert!(
+                bins.windows(2).all(|w| w[0] < w[1]),
+                "Histogram bin edges must be increasing"
+            );
+        }
+        let context = Context {
+            dataset_id,
+            assembly_id,
+            total_samples: 0,
+            has_ns: false,
+            ann_fields: AnnFields::default(),
+            csq_fields: CsqFields::default(),
+            mapping: input.mapping(),
+            transcript_policy: input.transcript_policy,
+            transcript_source: input.transcript_source,
+            transcripts: input.transcripts(),
+            coverage_tag: input.coverage_tag.clone(),
+            gq_tag: input.gq_tag.clone(),
+            distributions: input.distribution.clone(),
+            scores: input.score.clone(),
+            aggregations: input.aggregate.iter().cloned().collect(),
+            coverage_bins: input.coverage_bins.clone(),
+            gq_bins: input.gq_bins.clone(),
+            percentiles: input.percentiles.clone(),
+            strict: input.strict,
+            samples: None,
+        };
+
+        let mut reader = VariantReader {
+            inputs: inputs(input).into(),
+            regions,
+            decompress_threads: input.decompress_threads,
+            bcf: None,
+            context,
+            do_filter: !input.dont_filter,
+            panel: input.panel(),
+            genes: input.genes(),
+            reference: input.reference(),
+            normalize: input.normalize,
+            liftover: input.liftover(),
+            clinvar: input.annotation_file(&input.clinvar),
+            gnomad: input.annotation_file(&input.gnomad),
+            dbsnp: input.annotation_file(&input.dbsnp),
+            script: input.script.as_deref().map(Script::load),
+            vrs: input.vrs.then(Vrs::new),
+            spdi: input.spdi.as_deref().map(Spdi::load),
+            privacy: input.privacy(),
+            sample_filter: input.sample_filter(),
+            pseudonyms: input.pseudonyms(),
+            contig_names: input.contig_names(),
+            resume_from: None,
+            split_multiallelics: input.split_multiallelics,
+            star_alleles: input.star_alleles,
+            skip_svs: input.skip_svs,
+            skip_bnd: input.skip_bnd,
+            min_call_rate: input.min_call_rate,
+            min_allele_count: input.min_allele_count,
+            min_carriers: input.min_carriers,
+            consequences: input.consequence_filter(),
+            pending: VecDeque::new(),
+            last_position: Position::default(),
+            progress: Progress::hidden(),
+            report: None,
+            counts: Counts::default(),
+        };
+        reader.open_next();
+        reader
+    }
+
+    /// Open the next file, returning false when there are no files left.
+    fn open_next(&mut self) -> bool {
+        let input = match self.inputs.pop_front() {
+            Some(input) => input,
+            None => return false,
+        };
+
+        let bcf = match &input {
+            Input::File(path) => Source::open(path, self.regions.clone()).map_err(Into::into),
+            Input::Htsget { endpoint, region } => Source::download(endpoint, region.clone()),
+        };
+        let mut bcf = bcf.unwrap_or_else(|e| panic!("Error opening {}: {}", input, e));
+
+        if self.decompress_threads > 0 {
+            bcf.set_threads(self.decompress_threads)
+                .unwrap_or_else(|e| panic!("Error setting threads for {}: {}", input, e));
+        }
+
+        self.context.samples = self
+            .sample_filter
+            .as_ref()
+            .map(|filter| filter.mask(bcf.header(), &input, &self.pseudonyms));
+        self.context.total_samples = match &self.context.samples {
+            Some(mask) => mask.iter().filter(|&&x| x).count() as u32,
+            None => bcf.header().sample_count(),
+        };
+
+        self.context.has_ns = bcf.header().info_type(NS.as_bytes()).is_ok();
+        self.context.ann_fields = AnnFields::from_header(bcf.header());
+        self.context.csq_fields = CsqFields::from_header(bcf.header());
+
+        self.bcf = Some(bcf);
+        self.counts.files += 1;
+        true
+    }
+
+    pub fn set_progress(&mut self, progress: Progress) {
+        self.progress = progress;
+    }
+
+    /// Skip records up to and including the one at position.
+    pub fn resume_from(&mut self, position: Position) {
+        self.last_position = position.clone();
+        self.resume_from = Some(position);
+    }
+
+    fn in_genes(&self, v: &Variant) -> bool {
+        match (&self.genes, &v.gene_symbol) {
+            (None, _) => true,
+            (Some(genes), Some(symbols)) => symbols.iter().any(|x| genes.contains(x)),
+            (Some(_), None) => false,
+        }
+    }
+
+    /// Leave out gVCF reference blocks, remove symbolic and * alleles and
+    /// split the variants of a record if requested, then transform each one.
+    fn convert(&mut self, record: &Record) -> error::Result<Vec<Variant>> {
+        if let Some(filter) = &self.consequences {
+            if !filter.matches(&ann_entries(record)?, &self.context.ann_fields) {
+                self.counts.low_impact += 1;
+                return Ok(Vec::new());
+            }
+        }
+        let mut v = Variant::from_record(record, &self.context)?;
+        if v.is_reference_block() {
+            self.counts.reference_blocks += 1;
+            return Ok(Vec::new());
+        }
+        for allele in NON_REF {
+            v.remove_allele(allele);
+        }
+        if (self.skip_svs && v.is_structural()) || (self.skip_bnd && v.mate.is_some()) {
+            self.counts.structural += 1;
+            return Ok(Vec::new());
+        }
+        if let (Some(min), Some(call_rate)) = (self.min_call_rate, v.call_rate) {
+            if call_rate < min {
+                self.counts.low_call_rate += 1;
+                return Ok(Vec::new());
+            }
+        }
+        if self.star_alleles == StarAlleles::Drop && v.remove_allele("*") {
+            self.counts.star_alleles += 1;
+            if v.alternate_bases.is_empty() {
+                return Ok(Vec::new());
+            }
+        }
+        if self.withhold_rare(&mut v) && v.alternate_bases.is_empty() {
+            return Ok(Vec::new());
+        }
+        let variants = if self.split_multiallelics {
+            v.split()
+        } else {
+            vec![v]
+        };
+        variants
+            .into_iter()
+            .filter_map(|v| self.transform(v).transpose())
+            .collect()
+    }
+
+    /// Remove ALT alleles below --min-allele-count or --min-carriers. Alleles
+    /// whose counts can't be computed, without genotypes or INFO/AC, are
+    /// removed too, so that nothing is uploaded that wasn't checked. True if
+    /// any allele was removed.
+    fn withhold_rare(&mut self, v: &mut Variant) -> bool {
+        if self.min_allele_count.is_none() && self.min_carriers.is_none() {
+            return false;
+        }
+        let below = |value: Option<u32>, min: Option<u32>| match (value, min) {
+            (_, None) => false,
+            (Some(value), Some(min)) => value < min,
+            (None, Some(_)) => true,
+        };
+        let rare: Vec<String> = v
+            .alternate_bases
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| {
+                let (count, carriers) = v.observations(i);
+                below(count, self.min_allele_count) || below(carriers, self.min_carriers)
+            })
+            .map(|(_, allele)| allele.clone())
+            .collect();
+        for allele in &rare {
+            v.remove_allele(allele);
+            self.counts.rare_alleles += 1;
+        }
+        !rare.is_empty()
+    }
+
+    /// Lift variant over to the target assembly, check it against the
+    /// reference, normalize it, compute its VRS identifiers and SPDI,
+    /// annotate it, rename its contig, set its id, run the script and blur
+    /// its statistics, in that order. None if the script left it out.
+    fn transform(&mut self, mut v: Variant) -> error::Result<Option<Variant>> {
+        if let Some(chain) = &self.liftover {
+            chain.lift_variant(&mut v)?;
+        }
+        self.check_ref(&mut v)?;
+        if let (true, Some(fasta)) = (self.normalize, &self.reference) {
+            normalize(&mut v, fasta)?;
+        }
+        if let (Some(vrs), Some(fasta)) = (&mut self.vrs, &self.reference) {
+            vrs.annotate(&mut v, fasta)?;
+        }
+        if let (Some(spdi), Some(fasta)) = (&self.spdi, &self.reference) {
+            spdi.annotate(&mut v, fasta)?;
+        }
+        if let Some(clinvar) = &mut self.clinvar {
+            clinvar::annotate(clinvar, &mut v)?;
+        }
+        if let Some(file) = &mut self.gnomad {
+            gnomad::annotate(file, &mut v)?;
+        }
+        if let (None, Some(dbsnp)) = (&v.snp_ids, &mut self.dbsnp) {
+            v.snp_ids = dbsnp.ids(&v)?;
+        }
+        v.reference_name = self.contig_names.rename(&v.reference_name);
+        if let Some(mate) = &mut v.mate {
+            mate.reference_name = self.contig_names.rename(&mate.reference_name);
+        }
+        v.set_id();
+        let mut v = match &self.script {
+            Some(script) => match script.run(v)? {
+                Some(v) => v,
+                None => {
+                    self.counts.vetoed += 1;
+                    return Ok(None);
+                }
+            },
+            None => v,
+        };
+        if let Some(privacy) = &self.privacy {
+            privacy.apply(&mut v);
+        }
+        Ok(Some(v))
+    }
+
+    /// Compare REF with the bases of the reference FASTA, if any, so that
+    /// records of another assembly are not imported. After liftover, REF and
+    /// ALT of biallelic variants are swapped when the new reference has ALT.
+    fn check_ref(&self, v: &mut Variant) -> error::Result<()> {
+        let fasta = match &self.reference {
+            Some(fasta) => fasta,
+            None => return Ok(()),
+        };
+        if v.reference_bases.is_empty() {
+            return Ok(());
+        }
+        let start = v.start as u64 - 1;
+        let expected = fasta
+            .fetch(
+                &v.reference_name,
+                start,
+                start + v.reference_bases.len() as u64 - 1,
+            )
+            .ok_or_else(|| error::Error::UnknownContig(v.reference_name.clone()))?;
+        let expected = String::from_utf8_lossy(&expected).to_string();
+        if v.reference_bases.eq_ignore_ascii_case(&expected) {
+            return Ok(());
+        }
+
+        let flip = self.liftover.is_some()
+            && v.alternate_bases.len() == 1
+            && v.alternate_bases[0].eq_ignore_ascii_case(&expected);
+        if !flip {
+            return Err(error::Error::RefMismatch {
+                found: v.reference_bases.clone(),
+                expected,
+            });
+        }
+        std::mem::swap(&mut v.reference_bases, &mut v.alternate_bases[0]);
+        for af in &mut v.allele_frequency {
+            *af = 1.0 - *af;
+        }
+        for counts in v.genotype_counts.iter_mut().flatten() {
+            std::mem::swap(&mut counts.hom_ref, &mut counts.hom_alt);
+        }
+        if let (Some(ac), Some(an)) = (&mut v.allele_count, v.allele_number) {
+            for x in ac {
+                *x = an.saturating_sub(*x);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn summary(&self) -> String {
+        self.counts.summary(self.do_filter)
+    }
+}
+
+impl Iterator for VariantReader {
+    type Item = (Position, Variant);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(item) = self.pending.pop_front() {
+            return Some(item);
+        }
+        let mut record = self.bcf.as_ref()?.empty_record();
+
+        loop {
+            let bcf = self.bcf.as_mut()?;
+            match bcf.read(&mut record) {
+                Some(Ok(())) => {}
+                // Unlike records that can't be converted, an unreadable
+                // record (e.g. a truncated file) leaves nothing to read
+                // after it.
+                Some(Err(e)) => panic!("Error reading record {}: {}", self.counts.total + 1, e),
+                None => {
+                    if !self.open_next() {
+                        self.bcf = None;
+                        return None;
+                    }
+                    record = self.bcf.as_ref()?.empty_record();
+                    continue;
+                }
+            }
+
+            self.counts.total += 1;
+            self.counts.contig(get_reference_name(&record)).total += 1;
+            self.progress.read();
+
+            if let Some(position) = &self.resume_from {
+                let current = u64::from(self.counts.total);
+                if current == position.record {
+                    assert!(
+                        get_reference_name(&record) == position.reference_name
+                            && record.pos() + 1 == position.start,
+                        "Checkpoint does not match VCF file at record {}",
+                        current
+                    );
+                }
+                if current <= position.record {
+                    self.counts.resumed += 1;
+                    continue;
+                }
+            }
+
+            if let Some(report) = &mut self.report {
+                report.read(&record);
+            }
+
+            if self.do_filter && !record.has_filter("PASS".as_bytes()) {
+                self.counts.filtered += 1;
+                continue;
+            }
+
+            if let Some(panel) = &self.panel {
+                if !panel.contains(&get_reference_name(&record), record.pos() as u64) {
+                    self.counts.filtered += 1;
+                    continue;
+                }
+            }
+
+            self.counts.passed += 1;
+            self.counts.contig(get_reference_name(&record)).passed += 1;
+
+            if let Some(report) = &mut self.report {
+                report.check(&record);
+            }
+
+            match self.convert(&record) {
+                Ok(variants) => {
+                    let n = variants.len();
+                    let mut variants: VecDeque<Variant> =
+                        variants.into_iter().filter(|v| self.in_genes(v)).collect();
+                    self.counts.other_genes += (n - variants.len()) as u32;
+                    let last = match variants.pop_back() {
+                        Some(last) => last,
+                        None => continue,
+                    };
+                    // Checkpoints keep coordinates of the VCF file.
+                    let position = Position {
+                        record: u64::from(self.counts.total),
+                        reference_name: get_reference_name(&record),
+                        start: record.pos() + 1,
+                    };
+                    // Resuming after an allele of a split record reads the
+                    // whole record again, so no allele is lost.
+                    let previous = std::mem::replace(&mut self.last_position, position.clone());
+                    self.pending
+                        .extend(variants.into_iter().map(|v| (previous.clone(), v)));
+                    self.pending.push_back((position, last));
+                    return self.pending.pop_front();
+                }
+                Err(e) if self.context.strict => Exit::ValidationFailed.fail(format!(
+                    "Record {} at {}:{} can't be imported: {}, use --lenient to skip it",
+                    self.counts.total,
+                    get_reference_name(&record),
+                    record.pos() + 1,
+                    e
+                )),
+                Err(e) => {
+                    warn!(
+                        "Skipping record {} at {}:{}: {}",
+                        self.counts.total,
+                        get_reference_name(&record),
+                        record.pos() + 1,
+                        e
+                    );
+                    if let Some(report) = &mut self.report {
+                        report.error(&record, &e);
+                    }
+                    self.counts.skipped += 1;
+                    *self.counts.errors.entry(e.kind()).or_default() += 1;
+                    self.counts.contig(get_reference_name(&record)).failed += 1;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Genomic interval with 0-based inclusive coordinates, as expected by
+/// `IndexedReader::fetch`. A missing end means up to the end of the contig.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub contig: String,
+    pub start: u64,
+    pub end: Option<u64>,
+}
+
+impl Region {
+    pub fn contains(&self, pos: u64) -> bool {
+        pos >= self.start && self.end.map_or(true, |end| pos <= end)
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.end {
+            Some(end) => write!(f, "{}:{}-{}", self.contig, self.start + 1, end + 1),
+            None => write!(f, "{}:{}-", self.contig, self.start + 1),
+        }
+    }
+}
+
+/// Parse regions written as `chr1`, `chr1:1000` or `chr1:1000-2000`, with
+/// 1-based inclusive coordinates like samtools and bcftools.
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (contig, range) = match s.rsplit_once(':') {
+            Some((contig, range)) => (contig, Some(range)),
+            None => (s, None),
+        };
+        if contig.is_empty() {
+            return Err(format!("missing contig in region {}", s));
+        }
+
+        let parse = |x: &str| {
+            x.replace(',', "")
+                .parse::<u64>()
+                .ok()
+                .filter(|&x| x > 0)
+                .ok_or_else(|| format!("invalid position {} in region {}", x, s))
+        };
+
+        let (start, end) = match range.map(|x| x.split_once('-')) {
+            None => (0, None),
+            Some(None) => (parse(range.unwrap())? - 1, None),
+            Some(Some((start, ""))) => (parse(start)? - 1, None),
+            Some(Some((start, end))) => (parse(start)? - 1, Some(parse(end)? - 1)),
+        };
+        if end.is_some_and(|end| end < start) {
+            return Err(format!("end before start in region {}", s));
+        }
+
+        Ok(Region {
+            contig: contig.to_string(),
+            start,
+            end,
+        })
+    }
+}
+
+/// Read regions from a tab-delimited file with CHROM, BEG and END columns
+/// (1-based, inclusive), or from a BED file (0-based, half-open) if the file
+/// name ends with `.bed`. END may be omitted to select a single position.
+pub fn read_regions_file(path: &Path) -> io::Result<Vec<Region>> {
+    let is_bed = path.extension().is_some_and(|x| x == "bed");
+    read_file(path, is_bed)
+}
+
+/// Read regions from a BED file, whatever its file name.
+pub fn read_bed_file(path: &Path) -> io::Result<Vec<Region>> {
+    read_file(path, true)
+}
+
+fn read_file(path: &Path, is_bed: bool) -> io::Result<Vec<Region>> {
+    let content = fs::read_to_string(path)?;
+
+    let invalid = |line: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid region in {:?}: {}", path, line),
+        )
+    };
+
+    let mut regions = Vec::new();
+    for line in content.lines() {
+        if line.is_empty() || line.starts_with('#') || line.starts_with("track") {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 2 {
+            return Err(invalid(line));
+        }
+        let start: u64 = fields[1].parse().map_err(|_| invalid(line))?;
+        let end: u64 = match fields.get(2) {
+            Some(end) => end.parse().map_err(|_| invalid(line))?,
+            None if is_bed => return Err(invalid(line)),
+            None => start,
+        };
+        let (start, end) = if is_bed && start == end {
+            // A zero-length interval is an insertion point, and VCF records
+            // of insertions start at the base before it.
+            let anchor = start.saturating_sub(1);
+            (anchor, anchor)
+        } else if is_bed {
+            (start, end.checked_sub(1).ok_or_else(|| invalid(line))?)
+        } else {
+            (
+                start.checked_sub(1).ok_or_else(|| invalid(line))?,
+                end.checked_sub(1).ok_or_else(|| invalid(line))?,
+            )
+        };
+        if end < start {
+            return Err(invalid(line));
+        }
+        regions.push(Region {
+            contig: fields[0].to_string(),
+            start,
+            end: Some(end),
+        });
+    }
+    Ok(regions)
+}
+
+/// Sort regions by contig order and position, merging overlapping ones so
+/// that no record is read twice.
+pub fn merge(mut regions: Vec<Region>, contig_order: impl Fn(&str) -> Option<u32>) -> Vec<Region> {
+    regions.sort_by_key(|r| (contig_order(&r.contig), r.contig.clone(), r.start));
+
+    let mut merged: Vec<Region> = Vec::with_capacity(regions.len());
+    for region in regions {
+        if let Some(last) = merged.last_mut() {
+            let overlaps = last.contig == region.contig
+                && last.end.map_or(true, |end| region.start <= end + 1);
+            if overlaps {
+                last.end = match (last.end, region.end) {
+                    (Some(a), Some(b)) => Some(a.max(b)),
+                    _ => None,
+                };
+                continue;
+            }
+        }
+        merged.push(region);
+    }
+    merged
+}
+
+/// Regions merged and sorted per contig, so checking whether a position is
+/// covered is a binary search.
+pub struct RegionSet {
+    contigs: HashMap<String, Vec<(u64, u64)>>,
+}
+
+impl RegionSet {
+    pub fn new(regions: Vec<Region>) -> Self {
+        let mut contigs: HashMap<String, Vec<(u64, u64)>> = HashMap::new();
+        for region in merge(regions, |_| None) {
+            contigs
+                .entry(region.contig)
+                .or_default()
+                .push((region.start, region.end.unwrap_or(u64::MAX)));
+        }
+        RegionSet { contigs }
+    }
+
+    pub fn contains(&self, contig: &str, pos: u64) -> bool {
+        let intervals = match self.contigs.get(contig) {
+            Some(intervals) => intervals,
+            None => return false,
+        };
+        // Index of the first interval starting after pos.
+        let i = intervals.partition_point(|&(start, _)| start <= pos);
+        i > 0 && pos <= intervals[i - 1].1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(contig: &str, start: u64, end: Option<u64>) -> Region {
+        Region {
+            contig: contig.to_string(),
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn regions_are_parsed_as_1_based_inclusive() {
+        assert_eq!("chr1".parse(), Ok(region("chr1", 0, None)));
+        assert_eq!("chr1:1,000".parse(), Ok(region("chr1", 999, None)));
+        assert_eq!("chr1:1000-".parse(), Ok(region("chr1", 999, None)));
+        assert_eq!(
+            "chr1:1000-2000".parse(),
+            Ok(region("chr1", 999, Some(1999)))
+        );
+        assert!("chr1:0".parse::<Region>().is_err());
+        assert!("chr1:2000-1000".parse::<Region>().is_err());
+        assert!(":1000".parse::<Region>().is_err());
+    }
+
+    #[test]
+    fn bed_intervals_are_0_based_half_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("panel.bed");
+        fs::write(
+            &path,
+            "track name=panel\n# comment\nchr1\t0\t10\tGENE1\nchr1\t100\t100\nchr2\t0\t0\n",
+        )
+        .unwrap();
+        assert_eq!(
+            read_regions_file(&path).unwrap(),
+            vec![
+                region("chr1", 0, Some(9)),
+                region("chr1", 99, Some(99)),
+                region("chr2", 0, Some(0)),
+            ]
+        );
+
+        fs::write(&path, "chr1\t10\t5\n").unwrap();
+        assert!(read_bed_file(&path).is_err());
+        fs::write(&path, "chr1\t10\n").unwrap();
+        assert!(read_bed_file(&path).is_err());
+    }
+
+    #[test]
+    fn regions_files_are_1_based_inclusive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("regions.tsv");
+        fs::write(&path, "chr1\t1\t10\nchr1\t100\n").unwrap();
+        assert_eq!(
+            read_regions_file(&path).unwrap(),
+            vec![region("chr1", 0, Some(9)), region("chr1", 99, Some(99))]
+        );
+
+        fs::write(&path, "chr1\t0\t10\n").unwrap();
+        assert!(read_regions_file(&path).is_err());
+    }
+
+    #[test]
+    fn region_set_contains_positions_of_merged_intervals() {
+        let set = RegionSet::new(vec![
+            region("chr1", 10, Some(19)),
+            region("chr1", 15, Some(29)),
+            region("chr1", 99, Some(99)),
+            region("chr2", 0, None),
+        ]);
+        assert!(!set.contains("chr1", 9));
+        assert!(set.contains("chr1", 10));
+        assert!(set.contains("chr1", 29));
+        assert!(!set.contains("chr1", 30));
+        assert!(set.contains("chr1", 99));
+        assert!(set.contains("chr2", 1 << 40));
+        assert!(!set.contains("chr3", 10));
+    }
+}
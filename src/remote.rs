@@ -0,0 +1,89 @@
+use crate::cli::ServerOpts;
+use crate::exit::Exit;
+use crate::region::Region;
+use crate::upload::{rejected, request_error};
+use crate::variant::Variant;
+use reqwest::blocking::Client;
+use std::collections::VecDeque;
+
+const PAGE_SIZE: usize = 1000;
+
+/// Query parameters selecting the variants of dataset, optionally only those
+/// of assembly and in region. Positions are 1-based on the server, like the
+/// start of variants.
+pub fn variant_query(
+    dataset: &str,
+    assembly: Option<&str>,
+    region: Option<&Region>,
+) -> Vec<(&'static str, String)> {
+    let mut query = vec![("datasetId", dataset.to_string())];
+    if let Some(assembly) = assembly {
+        query.push(("assemblyId", assembly.to_string()));
+    }
+    if let Some(region) = region {
+        query.push(("referenceName", region.contig.clone()));
+        query.push(("start", (region.start + 1).to_string()));
+        if let Some(end) = region.end {
+            query.push(("end", (end + 1).to_string()));
+        }
+    }
+    query
+}
+
+/// Variants stored on the server, fetched one page at a time.
+pub struct VariantPages<'a> {
+    server: &'a ServerOpts,
+    client: Client,
+    query: Vec<(&'static str, String)>,
+    page: usize,
+    buffer: VecDeque<Variant>,
+    done: bool,
+}
+
+impl<'a> VariantPages<'a> {
+    pub fn new(server: &'a ServerOpts, query: Vec<(&'static str, String)>) -> Self {
+        VariantPages {
+            server,
+            client: server.client(),
+            query,
+            page: 0,
+            buffer: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    fn fetch_page(&mut self) {
+        let url = format!("{}/variants", self.server.host());
+        let page = self.page.to_string();
+        let size = PAGE_SIZE.to_string();
+        let res = self
+            .server
+            .auth()
+            .apply(self.client.get(&url))
+            .query(&self.query)
+            .query(&[("page", &page), ("size", &size)])
+            .send()
+            .unwrap_or_else(|e| Exit::for_error(&e).fail(request_error(&e)));
+        if !res.status().is_success() {
+            rejected(res);
+        }
+
+        let variants: Vec<Variant> = res
+            .json()
+            .unwrap_or_else(|e| panic!("Invalid response from {}: {}", url, e));
+        self.done = variants.len() < PAGE_SIZE;
+        self.buffer.extend(variants);
+        self.page += 1;
+    }
+}
+
+impl Iterator for VariantPages<'_> {
+    type Item = Variant;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buffer.is_empty() && !self.done {
+            self.fetch_page();
+        }
+        self.buffer.pop_front()
+    }
+}
@@ -0,0 +1,115 @@
+use crate::error::Error;
+use crate::variant::get_reference_name;
+use rust_htslib::bcf::Record;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Record with a problem, 1-based position like in the VCF file.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Example {
+    reference_name: String,
+    position: i64,
+    problem: String,
+}
+
+/// Machine-readable summary of what an import would send, written on
+/// dry runs so a VCF file can be fixed before going live.
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    records: u64,
+    /// Records per FILTER value, `.` when no filter was applied.
+    filters: BTreeMap<String, u64>,
+    /// Records without each of the checked INFO and FORMAT fields.
+    missing_fields: BTreeMap<String, u64>,
+    /// Records that can't be converted, per kind of error.
+    errors: BTreeMap<String, u64>,
+    examples: Vec<Example>,
+    #[serde(skip)]
+    max_examples: usize,
+    /// Fields whose absence is reported, although variants can be built
+    /// without them: AF, the coverage and GQ FORMAT tags and ANN.
+    #[serde(skip)]
+    checked_fields: Vec<String>,
+}
+
+impl Report {
+    pub fn new(max_examples: usize, coverage_tag: &str, gq_tag: &str) -> Self {
+        Report {
+            max_examples,
+            checked_fields: vec![
+                "AF".to_string(),
+                coverage_tag.to_string(),
+                gq_tag.to_string(),
+                "ANN".to_string(),
+            ],
+            ..Default::default()
+        }
+    }
+
+    /// Count FILTER values of every record read.
+    pub fn read(&mut self, record: &Record) {
+        self.records += 1;
+        let header = record.header();
+        let filters: Vec<String> = record
+            .filters()
+            .map(|id| String::from_utf8_lossy(&header.id_to_name(id)).to_string())
+            .collect();
+        let key = if filters.is_empty() {
+            ".".to_string()
+        } else {
+            filters.join(";")
+        };
+        *self.filters.entry(key).or_default() += 1;
+    }
+
+    /// Check fields of a record that passed filters.
+    pub fn check(&mut self, record: &Record) {
+        for i in 0..self.checked_fields.len() {
+            let field = self.checked_fields[i].clone();
+            let present = match field.as_str() {
+                "AF" => record.info(b"AF").float().is_ok_and(|x| x.is_some()),
+                "ANN" => record.info(b"ANN").string().is_ok_and(|x| x.is_some()),
+                tag => {
+                    record.format(tag.as_bytes()).integer().is_ok()
+                        || record.format(tag.as_bytes()).float().is_ok()
+                }
+            };
+            if !present {
+                self.example(record, format!("missing {}", field));
+                *self.missing_fields.entry(field).or_default() += 1;
+            }
+        }
+    }
+
+    pub fn error(&mut self, record: &Record, error: &Error) {
+        *self.errors.entry(error.kind()).or_default() += 1;
+        self.example(record, error.to_string());
+    }
+
+    fn example(&mut self, record: &Record, problem: String) {
+        if self.examples.len() < self.max_examples {
+            self.examples.push(Example {
+                reference_name: get_reference_name(record),
+                position: record.pos() + 1,
+                problem,
+            });
+        }
+    }
+
+    pub fn merge(&mut self, other: Report) {
+        self.records += other.records;
+        for (key, n) in other.filters {
+            *self.filters.entry(key).or_default() += n;
+        }
+        for (key, n) in other.missing_fields {
+            *self.missing_fields.entry(key).or_default() += n;
+        }
+        for (key, n) in other.errors {
+            *self.errors.entry(key).or_default() += n;
+        }
+        let room = self.max_examples.saturating_sub(self.examples.len());
+        self.examples.extend(other.examples.into_iter().take(room));
+    }
+}
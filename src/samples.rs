@@ -0,0 +1,67 @@
+use crate::pseudonym::Pseudonyms;
+use log::debug;
+use rust_htslib::bcf::header::HeaderView;
+use std::collections::HashSet;
+use std::fmt::Display;
+
+/// Samples whose genotypes and FORMAT values are used for statistics,
+/// from --samples, --samples-file and --exclude-samples.
+#[derive(Clone)]
+pub struct SampleFilter {
+    pub include: Option<HashSet<String>>,
+    pub exclude: HashSet<String>,
+}
+
+impl SampleFilter {
+    /// Whether each sample of a file is used, in header order. Panics if a
+    /// sample given is not in the file, which is likely a typo.
+    pub fn mask(
+        &self,
+        header: &HeaderView,
+        input: &impl Display,
+        pseudonyms: &Pseudonyms,
+    ) -> Vec<bool> {
+        let names: HashSet<String> = header
+            .samples()
+            .iter()
+            .map(|x| String::from_utf8_lossy(x).to_string())
+            .collect();
+        let given = self.include.iter().flatten().chain(&self.exclude);
+        for sample in given {
+            assert!(
+                names.contains(sample),
+                "Sample {} not in {}",
+                pseudonyms.name(sample),
+                input
+            );
+        }
+        header
+            .samples()
+            .iter()
+            .map(|x| {
+                let name = String::from_utf8_lossy(x);
+                let keep = self
+                    .include
+                    .as_ref()
+                    .map_or(true, |x| x.contains(name.as_ref()))
+                    && !self.exclude.contains(name.as_ref());
+                if !keep {
+                    debug!("Leaving out sample {}", pseudonyms.name(&name));
+                }
+                keep
+            })
+            .collect()
+    }
+}
+
+/// Values of the samples used, or of every sample without a mask.
+pub fn select<T>(values: Vec<T>, mask: Option<&[bool]>) -> Vec<T> {
+    match mask {
+        Some(mask) => values
+            .into_iter()
+            .zip(mask)
+            .filter_map(|(x, &keep)| keep.then_some(x))
+            .collect(),
+        None => values,
+    }
+}
@@ -0,0 +1,37 @@
+use crate::variant::Variant;
+use schemars::schema_for;
+use serde_json::Value;
+
+/// Print the JSON Schema of the variants sent to the server and written by
+/// export, one of which is each line of an NDJSON file.
+pub fn run() {
+    let schema = schema_for!(Variant);
+    println!("{}", serde_json::to_string_pretty(&schema).unwrap());
+}
+
+/// Schema with references replaced by their definition, and whether it
+/// allows null.
+pub fn resolve(schema: &Value, definitions: &Value) -> (Value, bool) {
+    if let Some(reference) = schema["$ref"].as_str() {
+        let name = reference.rsplit('/').next().unwrap_or_default();
+        return resolve(&definitions[name], definitions);
+    }
+    for key in ["allOf", "anyOf", "oneOf"] {
+        if let Some(schemas) = schema[key].as_array() {
+            let nullable = schemas.iter().any(|x| types(x) == ["null"]);
+            if let Some(inner) = schemas.iter().find(|x| types(x) != ["null"]) {
+                let (inner, inner_nullable) = resolve(inner, definitions);
+                return (inner, nullable || inner_nullable);
+            }
+        }
+    }
+    (schema.clone(), false)
+}
+
+pub fn types(schema: &Value) -> Vec<&str> {
+    match &schema["type"] {
+        Value::String(x) => vec![x.as_str()],
+        Value::Array(x) => x.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
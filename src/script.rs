@@ -0,0 +1,37 @@
+use crate::error::{Error, Result};
+use crate::variant::Variant;
+use rhai::{Dynamic, Engine, Scope, AST};
+use std::path::Path;
+
+/// Rhai script defining `fn transform(variant)`, called with each variant
+/// as an object map named like the payload fields. It returns the variant,
+/// changed or not, or `()` to leave it out of the import.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+}
+
+impl Script {
+    pub fn load(path: &Path) -> Self {
+        let engine = Engine::new();
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e));
+        Script { engine, ast }
+    }
+
+    /// Run the script on a variant, returning None if it was vetoed.
+    pub fn run(&self, v: Variant) -> Result<Option<Variant>> {
+        let variant = rhai::serde::to_dynamic(v).map_err(|e| Error::Script(e.to_string()))?;
+        let result: Dynamic = self
+            .engine
+            .call_fn(&mut Scope::new(), &self.ast, "transform", (variant,))
+            .map_err(|e| Error::Script(e.to_string()))?;
+        if result.is_unit() {
+            return Ok(None);
+        }
+        rhai::serde::from_dynamic(&result)
+            .map(Some)
+            .map_err(|e| Error::Script(e.to_string()))
+    }
+}
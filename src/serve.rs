@@ -0,0 +1,240 @@
+use crate::cli::ServeOpts;
+use crate::watch;
+use log::{error, info};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server};
+use url::Url;
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Job {
+    id: u64,
+    dataset: String,
+    assembly: String,
+    status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+type Jobs = Arc<Mutex<BTreeMap<u64, Job>>>;
+
+/// Requests handled at once, so that a slow upload doesn't hold up the
+/// others.
+const HANDLERS: usize = 4;
+
+/// Where uploads are saved until they are imported.
+struct Spool {
+    dir: PathBuf,
+    /// Largest upload accepted, in bytes.
+    max_size: u64,
+    next_id: AtomicU64,
+}
+
+/// Accept VCF files over HTTP and import them one after another in the
+/// background:
+///
+/// - `POST /jobs?dataset=D&assembly=A` with the VCF as body queues a job
+/// - `GET /jobs` lists the jobs
+/// - `GET /jobs/ID` shows the status of a job
+///
+/// Jobs are kept in memory, so they are gone after a restart.
+pub fn run(opts: ServeOpts) {
+    fs::create_dir_all(&opts.spool_dir)
+        .unwrap_or_else(|e| panic!("Error creating {:?}: {}", opts.spool_dir, e));
+    let server = Server::http(&opts.listen)
+        .unwrap_or_else(|e| panic!("Can't listen on {}: {}", opts.listen, e));
+    info!("Listening on {}", opts.listen);
+    let server = Arc::new(server);
+    let spool = Arc::new(Spool {
+        dir: opts.spool_dir,
+        max_size: opts.max_upload_size.saturating_mul(1 << 20),
+        next_id: AtomicU64::new(1),
+    });
+
+    let jobs: Jobs = Default::default();
+    let (queue, jobs_queued) = mpsc::channel::<(u64, PathBuf)>();
+    let worker = {
+        let jobs = jobs.clone();
+        let args = opts.import_args.clone();
+        thread::spawn(move || {
+            for (id, path) in jobs_queued {
+                let (dataset, assembly) = {
+                    let mut jobs = jobs.lock().unwrap();
+                    let job = jobs.get_mut(&id).unwrap();
+                    job.status = Status::Running;
+                    (job.dataset.clone(), job.assembly.clone())
+                };
+                info!(
+                    "Importing job {} into dataset {} ({})",
+                    id, dataset, assembly
+                );
+                let (status, message) = match watch::import(&dataset, &assembly, &args, &path) {
+                    Ok(status) if status.success() => (Status::Done, None),
+                    Ok(status) => (
+                        Status::Failed,
+                        Some(format!("Import failed with {}", status)),
+                    ),
+                    Err(e) => (Status::Failed, Some(format!("Can't run import: {}", e))),
+                };
+                match &message {
+                    Some(message) => error!("Job {}: {}", id, message),
+                    None => info!("Job {} done", id),
+                }
+                if let Err(e) = fs::remove_file(&path) {
+                    error!("Error removing {:?}: {}", path, e);
+                }
+                let mut jobs = jobs.lock().unwrap();
+                let job = jobs.get_mut(&id).unwrap();
+                job.status = status;
+                job.message = message;
+            }
+        })
+    };
+
+    let handlers: Vec<_> = (0..HANDLERS)
+        .map(|_| {
+            let (server, jobs, queue, spool) =
+                (server.clone(), jobs.clone(), queue.clone(), spool.clone());
+            let token = opts.token.clone();
+            thread::spawn(move || {
+                for mut request in server.incoming_requests() {
+                    let (code, body) = if authorized(&request, token.as_deref()) {
+                        handle(&mut request, &jobs, &queue, &spool)
+                    } else {
+                        (401, json!({"error": "Missing or wrong token"}))
+                    };
+                    let response = Response::from_string(body.to_string())
+                        .with_status_code(code)
+                        .with_header(
+                            Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                                .unwrap(),
+                        );
+                    if let Err(e) = request.respond(response) {
+                        error!("Error sending response: {}", e);
+                    }
+                }
+            })
+        })
+        .collect();
+    for handler in handlers {
+        handler.join().unwrap();
+    }
+    drop(queue);
+    worker.join().unwrap();
+}
+
+fn authorized(request: &Request, token: Option<&str>) -> bool {
+    let token = match token {
+        Some(token) => token,
+        None => return true,
+    };
+    request.headers().iter().any(|header| {
+        header.field.equiv("Authorization")
+            && header.value.as_str().strip_prefix("Bearer ") == Some(token)
+    })
+}
+
+fn handle(
+    request: &mut Request,
+    jobs: &Jobs,
+    queue: &Sender<(u64, PathBuf)>,
+    spool: &Spool,
+) -> (u16, serde_json::Value) {
+    let url = match Url::parse("http://localhost").and_then(|base| base.join(request.url())) {
+        Ok(url) => url,
+        Err(e) => return (400, json!({ "error": e.to_string() })),
+    };
+    let segments: Vec<_> = url.path_segments().into_iter().flatten().collect();
+    match (request.method(), &segments[..]) {
+        (Method::Post, ["jobs"]) => {
+            let params: HashMap<_, _> = url.query_pairs().collect();
+            let (dataset, assembly) = match (params.get("dataset"), params.get("assembly")) {
+                (Some(dataset), Some(assembly)) => (dataset.to_string(), assembly.to_string()),
+                _ => return (400, json!({"error": "dataset and assembly are required"})),
+            };
+            let too_large = || (413, json!({"error": "Upload too large"}));
+            if request
+                .body_length()
+                .is_some_and(|len| len as u64 > spool.max_size)
+            {
+                return too_large();
+            }
+            let id = spool.next_id.fetch_add(1, Ordering::SeqCst);
+            let path = match save(request.as_reader(), spool, id) {
+                Ok(Some(path)) => path,
+                Ok(None) => return too_large(),
+                Err(e) => return (500, json!({ "error": e.to_string() })),
+            };
+            let job = Job {
+                id,
+                dataset,
+                assembly,
+                status: Status::Queued,
+                message: None,
+            };
+            info!("Queued job {} for dataset {}", id, job.dataset);
+            jobs.lock().unwrap().insert(id, job.clone());
+            queue.send((id, path)).unwrap();
+            (202, json!(job))
+        }
+        (Method::Get, ["jobs"]) => (
+            200,
+            json!(jobs.lock().unwrap().values().collect::<Vec<_>>()),
+        ),
+        (Method::Get, ["jobs", id]) => {
+            match id
+                .parse()
+                .ok()
+                .and_then(|id: u64| jobs.lock().unwrap().get(&id).cloned())
+            {
+                Some(job) => (200, json!(job)),
+                None => (404, json!({"error": "No such job"})),
+            }
+        }
+        (_, ["jobs"]) | (_, ["jobs", _]) => (405, json!({"error": "Method not allowed"})),
+        _ => (404, json!({"error": "Not found"})),
+    }
+}
+
+/// Save the uploaded file to the spool directory, as `.vcf.gz` if it's
+/// compressed so that it can be read like any other input. None if the
+/// upload is larger than the spool accepts, as chunked uploads don't give
+/// their size upfront.
+fn save(body: &mut dyn Read, spool: &Spool, id: u64) -> io::Result<Option<PathBuf>> {
+    let mut magic = Vec::new();
+    (&mut *body).take(2).read_to_end(&mut magic)?;
+    let extension = if magic == [0x1f, 0x8b] {
+        "vcf.gz"
+    } else {
+        "vcf"
+    };
+    let path = spool.dir.join(format!("{}.{}", id, extension));
+    let mut file = File::create(&path)?;
+    let mut body = magic.as_slice().chain(body).take(spool.max_size + 1);
+    match io::copy(&mut body, &mut file) {
+        Ok(len) if len <= spool.max_size => Ok(Some(path)),
+        result => {
+            // Don't leave partial or oversized uploads behind.
+            fs::remove_file(&path)?;
+            result.map(|_| None)
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::contig::read_map;
+use crate::error::{Error, Result};
+use crate::fasta::Fasta;
+use crate::variant::Variant;
+use crate::vrs;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Canonical SPDI notation of ALT alleles, `sequence:position:deletion:insertion`
+/// with RefSeq accessions as sequence names and 0-based positions.
+pub struct Spdi {
+    accessions: HashMap<String, String>,
+}
+
+impl Spdi {
+    /// Read a file with two whitespace-separated columns, the contig name in
+    /// the reference FASTA file and its RefSeq accession.
+    pub fn load(path: &Path) -> Self {
+        let accessions =
+            read_map(path).unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e));
+        Spdi { accessions }
+    }
+
+    /// Set `spdi`, one per ALT allele, unless the contig has no accession or
+    /// an allele is symbolic.
+    pub fn annotate(&self, v: &mut Variant, fasta: &Fasta) -> Result<()> {
+        let accession = match self.accessions.get(&v.reference_name) {
+            Some(accession) => accession,
+            None => return Ok(()),
+        };
+        if !vrs::is_sequence(&v.reference_bases)
+            || !v.alternate_bases.iter().all(|x| vrs::is_sequence(x))
+        {
+            return Ok(());
+        }
+        let unknown = || Error::UnknownContig(v.reference_name.clone());
+        let len = fasta.len(&v.reference_name).ok_or_else(unknown)?;
+        let start = v.start as u64 - 1;
+        let reference = v.reference_bases.to_ascii_uppercase().into_bytes();
+        v.spdi = v
+            .alternate_bases
+            .iter()
+            .map(|alt| {
+                let alt = alt.to_ascii_uppercase().into_bytes();
+                let (start, end, insertion) =
+                    vrs::normalize(fasta, &v.reference_name, len, start, &reference, &alt)
+                        .ok_or_else(unknown)?;
+                let deletion = if end > start {
+                    fasta
+                        .fetch(&v.reference_name, start, end - 1)
+                        .ok_or_else(unknown)?
+                        .to_ascii_uppercase()
+                } else {
+                    Vec::new()
+                };
+                Ok(format!(
+                    "{}:{}:{}:{}",
+                    accession,
+                    start,
+                    String::from_utf8_lossy(&deletion),
+                    String::from_utf8_lossy(&insertion)
+                ))
+            })
+            .collect::<Result<_>>()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    fn variant(contig: &str, start: i64, reference: &str, alternate: &[&str]) -> Variant {
+        serde_json::from_value(json!({
+            "datasetId": "test",
+            "assemblyId": "GRCh38",
+            "totalSamples": 0,
+            "referenceName": contig,
+            "start": start,
+            "referenceBases": reference,
+            "alternateBases": alternate,
+            "alleleFrequency": [],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn alleles_are_written_as_canonical_spdi() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ref.fa");
+        // A run of T from 7 to 11, 0-based.
+        fs::write(&path, ">1\nACGTACGTTTTTGCA\n>2\nACGT\n").unwrap();
+        let fasta = Fasta::open(&path).unwrap();
+        let spdi = Spdi {
+            accessions: HashMap::from([("1".to_string(), "NC_000001.11".to_string())]),
+        };
+        let spdi = |v: &mut Variant| {
+            spdi.annotate(v, &fasta).unwrap();
+            v.spdi.clone()
+        };
+
+        assert_eq!(
+            spdi(&mut variant("1", 2, "C", &["T", "G"])),
+            vec!["NC_000001.11:1:C:T", "NC_000001.11:1:C:G"]
+        );
+        // Indels in a repeat span all of it, wherever the VCF places them.
+        assert_eq!(
+            spdi(&mut variant("1", 11, "TT", &["T"])),
+            vec!["NC_000001.11:7:TTTTT:TTTT"]
+        );
+        assert_eq!(
+            spdi(&mut variant("1", 7, "GT", &["G", "GTT"])),
+            vec!["NC_000001.11:7:TTTTT:TTTT", "NC_000001.11:7:TTTTT:TTTTTT"]
+        );
+        assert!(spdi(&mut variant("1", 7, "G", &["<DEL>"])).is_empty());
+        assert!(spdi(&mut variant("2", 1, "A", &["T"])).is_empty());
+    }
+}
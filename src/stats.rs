@@ -0,0 +1,66 @@
+use crate::cli::StatsOpts;
+use rust_htslib::bcf::{Read, Reader};
+
+#[derive(Default)]
+struct Stats {
+    total: u32,
+    passed: u32,
+    multiallelic: u32,
+    snvs: u32,
+    mnvs: u32,
+    indels: u32,
+    others: u32,
+}
+
+pub fn run(opts: StatsOpts) {
+    let mut bcf = Reader::from_path(&opts.vcf_file).expect("Error opening file.");
+
+    let samples = bcf.header().sample_count();
+    let mut stats = Stats::default();
+
+    for record in bcf.records() {
+        let record =
+            record.unwrap_or_else(|e| panic!("Error reading record {}: {}", stats.total + 1, e));
+
+        stats.total += 1;
+
+        if record.has_filter("PASS".as_bytes()) {
+            stats.passed += 1;
+        }
+
+        let alleles = record.alleles();
+        if alleles.len() > 2 {
+            stats.multiallelic += 1;
+        }
+
+        let reference = alleles[0];
+        for alternate in alleles.iter().skip(1) {
+            if is_symbolic(alternate) {
+                stats.others += 1;
+            } else if reference.len() != alternate.len() {
+                stats.indels += 1;
+            } else if reference.len() == 1 {
+                stats.snvs += 1;
+            } else {
+                stats.mnvs += 1;
+            }
+        }
+    }
+
+    println!("Samples: {}", samples);
+    println!("Total variants: {}", stats.total);
+    println!("Passed variants: {}", stats.passed);
+    println!("Multiallelic variants: {}", stats.multiallelic);
+    println!("SNVs: {}", stats.snvs);
+    println!("MNVs: {}", stats.mnvs);
+    println!("Indels: {}", stats.indels);
+    println!("Other alleles: {}", stats.others);
+}
+
+/// Symbolic (<DEL>), breakend (N[chr1:10[), missing (.) and spanning
+/// deletion (*) alleles don't describe a sequence change.
+fn is_symbolic(allele: &[u8]) -> bool {
+    allele
+        .iter()
+        .any(|&b| matches!(b, b'<' | b'[' | b']' | b'.' | b'*'))
+}
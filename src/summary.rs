@@ -0,0 +1,211 @@
+use crate::progress::Progress;
+use crate::reader::{ContigCounts, Counts};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// Counts of a contig, with the number of its variants uploaded.
+#[derive(Serialize)]
+pub struct ContigSummary {
+    total: u32,
+    passed: u32,
+    failed: u32,
+    uploaded: u64,
+}
+
+impl ContigSummary {
+    fn new(counts: &ContigCounts, uploaded: u64) -> Self {
+        ContigSummary {
+            total: counts.total,
+            passed: counts.passed,
+            failed: counts.failed,
+            uploaded,
+        }
+    }
+}
+
+/// Outcome of an import, written with --summary-file for workflow engines
+/// to check.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Summary {
+    /// Records read from the VCF files.
+    total: u32,
+    /// Records skipped by --resume, imported by an earlier run.
+    resumed: u32,
+    /// Records passing the FILTER and --bed checks.
+    passed: u32,
+    /// Variants accepted by the server or sink.
+    uploaded: u64,
+    /// Records, variants or alleles left out, by reason.
+    skipped: BTreeMap<String, u64>,
+    /// Requests that failed and were retried or aborted the import.
+    failed_requests: u64,
+    duration_seconds: f64,
+    /// Uploaded variants per second.
+    throughput: f64,
+    /// Number of server responses by HTTP status code.
+    responses: BTreeMap<u16, u64>,
+    /// Counts by contig of the VCF files, to spot truncated files or
+    /// contigs whose records were all rejected.
+    contigs: BTreeMap<String, ContigSummary>,
+}
+
+impl Summary {
+    pub fn new(
+        counts: &Counts,
+        progress: &Progress,
+        already_imported: u64,
+        duration: Duration,
+    ) -> Self {
+        let mut skipped: BTreeMap<String, u64> = counts
+            .errors
+            .iter()
+            .map(|(kind, &n)| (kind.clone(), u64::from(n)))
+            .collect();
+        let reasons = [
+            ("filtered", counts.filtered),
+            ("structuralVariants", counts.structural),
+            ("otherGenes", counts.other_genes),
+            ("lowImpact", counts.low_impact),
+            ("lowCallRate", counts.low_call_rate),
+            ("referenceBlocks", counts.reference_blocks),
+            ("script", counts.vetoed),
+            ("rareAlleles", counts.rare_alleles),
+        ];
+        for (reason, n) in reasons {
+            if n > 0 {
+                skipped.insert(reason.to_string(), u64::from(n));
+            }
+        }
+        if already_imported > 0 {
+            skipped.insert("alreadyImported".to_string(), already_imported);
+        }
+
+        let uploaded = progress.uploaded_variants();
+        let uploaded_contigs = progress.contigs();
+        let contigs = counts
+            .contigs
+            .iter()
+            .map(|(name, counts)| {
+                let uploaded = uploaded_contigs.get(name).copied().unwrap_or_default();
+                (name.clone(), ContigSummary::new(counts, uploaded))
+            })
+            .collect();
+        Summary {
+            total: counts.total,
+            resumed: counts.resumed,
+            passed: counts.passed,
+            uploaded,
+            skipped,
+            failed_requests: progress.failed_requests(),
+            duration_seconds: duration.as_secs_f64(),
+            throughput: uploaded as f64 / duration.as_secs_f64().max(f64::EPSILON),
+            responses: progress.responses(),
+            contigs,
+        }
+    }
+
+    /// One line per contig for the log.
+    pub fn contig_lines(&self) -> Vec<String> {
+        self.contigs
+            .iter()
+            .map(|(name, x)| {
+                format!(
+                    "{}: {} records, {} passed, {} failed, {} uploaded",
+                    name, x.total, x.passed, x.failed, x.uploaded
+                )
+            })
+            .collect()
+    }
+
+    pub fn write(&self, path: &Path) {
+        let json = serde_json::to_string_pretty(self).unwrap();
+        fs::write(path, json + "\n").unwrap_or_else(|e| panic!("Error writing {:?}: {}", path, e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_every_skip_reason() {
+        let mut counts = Counts {
+            total: 12,
+            resumed: 2,
+            passed: 7,
+            filtered: 3,
+            skipped: 1,
+            structural: 1,
+            other_genes: 2,
+            ..Default::default()
+        };
+        counts.errors.insert("missing REF".to_string(), 1);
+        let progress = Progress::hidden();
+        progress.uploaded(3);
+
+        let summary = Summary::new(&counts, &progress, 1, Duration::from_secs(2));
+        assert_eq!(summary.total, 12);
+        assert_eq!(summary.resumed, 2);
+        assert_eq!(summary.uploaded, 3);
+        assert_eq!(summary.throughput, 1.5);
+        let skipped: Vec<_> = summary
+            .skipped
+            .iter()
+            .map(|(reason, &n)| (reason.as_str(), n))
+            .collect();
+        assert_eq!(
+            skipped,
+            [
+                ("alreadyImported", 1),
+                ("filtered", 3),
+                ("missing REF", 1),
+                ("otherGenes", 2),
+                ("structuralVariants", 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_are_broken_down_by_contig() {
+        let mut counts = Counts::default();
+        counts.contigs.insert(
+            "chr1".to_string(),
+            ContigCounts {
+                total: 12,
+                passed: 7,
+                failed: 1,
+            },
+        );
+        let progress = Progress::hidden();
+        progress.uploaded_contigs(&BTreeMap::from([("chr1".to_string(), 3)]));
+
+        let summary = Summary::new(&counts, &progress, 0, Duration::from_secs(1));
+        assert_eq!(
+            summary.contig_lines(),
+            ["chr1: 12 records, 7 passed, 1 failed, 3 uploaded"]
+        );
+    }
+
+    #[test]
+    fn summary_is_written_as_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("summary.json");
+        let counts = Counts {
+            total: 5,
+            passed: 5,
+            ..Default::default()
+        };
+        Summary::new(&counts, &Progress::hidden(), 0, Duration::from_secs(1)).write(&path);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["total"], 5);
+        assert_eq!(json["resumed"], 0);
+        assert_eq!(json["skipped"], serde_json::json!({}));
+        assert_eq!(json["failedRequests"], 0);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::cli::InputOpts;
+use crate::schema::{resolve, types};
+use crate::variant::Variant;
+use schemars::schema_for;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Writer of variants as a flat table, one row per ALT allele. Nested
+/// objects such as coverage are expanded into columns like
+/// `coverage.median`, and the scores, FORMAT distributions and percentiles
+/// into one column per name given in the options. Lists are joined with `;`.
+pub struct Table {
+    out: Box<dyn Write + Send>,
+    delimiter: char,
+    columns: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(out: Box<dyn Write + Send>, delimiter: char, input: &InputOpts) -> Self {
+        let keys = HashMap::from([
+            ("scores", input.score.clone()),
+            ("formatDistributions", input.distribution.clone()),
+            (
+                "percentiles",
+                input
+                    .percentiles
+                    .iter()
+                    .map(|p| format!("p{}", p))
+                    .collect(),
+            ),
+        ]);
+        let root = serde_json::to_value(schema_for!(Variant)).unwrap();
+        let definitions = root.get("definitions").cloned().unwrap_or_default();
+        let mut columns = Vec::new();
+        add_columns(&mut columns, &[], &root, &definitions, &keys);
+        Table {
+            out,
+            delimiter,
+            columns,
+        }
+    }
+
+    pub fn write_header(&mut self) -> io::Result<()> {
+        let header: Vec<_> = self.columns.iter().map(|path| path.join(".")).collect();
+        self.write_row(&header)
+    }
+
+    pub fn write(&mut self, v: &Variant) -> io::Result<()> {
+        for v in v.clone().split() {
+            let json = serde_json::to_value(&v).unwrap();
+            let row: Vec<_> = self
+                .columns
+                .iter()
+                .map(|path| lookup(&json, path).map(cell).unwrap_or_default())
+                .collect();
+            self.write_row(&row)?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    fn write_row(&mut self, cells: &[String]) -> io::Result<()> {
+        for (i, value) in cells.iter().enumerate() {
+            if i > 0 {
+                write!(self.out, "{}", self.delimiter)?;
+            }
+            let special = [self.delimiter, '"', '\n', '\r'];
+            if value.contains(special) {
+                write!(self.out, "\"{}\"", value.replace('"', "\"\""))?;
+            } else {
+                write!(self.out, "{}", value)?;
+            }
+        }
+        writeln!(self.out)
+    }
+}
+
+/// Add a column for every scalar or list of scalars under schema.
+fn add_columns(
+    columns: &mut Vec<Vec<String>>,
+    path: &[String],
+    schema: &Value,
+    definitions: &Value,
+    keys: &HashMap<&str, Vec<String>>,
+) {
+    let (schema, _) = resolve(schema, definitions);
+    let mut schema = schema;
+    // Lists of objects, such as genotypeCounts, have one item per allele.
+    if types(&schema).contains(&"array") {
+        let (items, _) = resolve(&schema["items"], definitions);
+        if !types(&items).contains(&"object") {
+            columns.push(path.to_vec());
+            return;
+        }
+        schema = items;
+    }
+    if !types(&schema).contains(&"object") {
+        columns.push(path.to_vec());
+        return;
+    }
+
+    let with = |name: &str| {
+        let mut path = path.to_vec();
+        path.push(name.to_string());
+        path
+    };
+    match schema["properties"].as_object() {
+        Some(properties) => {
+            for (name, property) in properties {
+                add_columns(columns, &with(name), property, definitions, keys);
+            }
+        }
+        None => {
+            let name = path.last().map(String::as_str).unwrap_or_default();
+            for key in keys.get(name).into_iter().flatten() {
+                let values = &schema["additionalProperties"];
+                add_columns(columns, &with(key), values, definitions, keys);
+            }
+        }
+    }
+}
+
+/// Value at path, taking the only item of lists of objects.
+fn lookup<'a>(json: &'a Value, path: &[String]) -> Option<&'a Value> {
+    let mut value = json;
+    for name in path {
+        if let Some(items) = value.as_array() {
+            value = items.first()?;
+        }
+        value = value.get(name)?;
+    }
+    Some(value)
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(x) => x.clone(),
+        Value::Array(items) => items.iter().map(cell).collect::<Vec<_>>().join(";"),
+        value => value.to_string(),
+    }
+}
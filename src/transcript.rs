@@ -0,0 +1,158 @@
+use crate::consequence::Impact;
+use crate::variant::AnnFields;
+use clap::ArgEnum;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Which ANN entries of an allele are sent.
+#[derive(ArgEnum, Clone, Copy, PartialEq)]
+pub enum TranscriptPolicy {
+    /// Transcripts listed in a file, one per line.
+    Canonical,
+    /// MANE Select transcripts of the NCBI MANE summary file.
+    Mane,
+    /// Entry with the highest impact.
+    Worst,
+    All,
+}
+
+/// Transcript namespace, recognized by the prefix of IDs.
+#[derive(ArgEnum, Clone, Copy, PartialEq)]
+pub enum TranscriptSource {
+    Refseq,
+    Ensembl,
+}
+
+impl TranscriptSource {
+    fn of(id: &str) -> Option<Self> {
+        if ["NM_", "NR_", "XM_", "XR_"]
+            .iter()
+            .any(|x| id.starts_with(x))
+        {
+            Some(TranscriptSource::Refseq)
+        } else if id.starts_with("ENST") {
+            Some(TranscriptSource::Ensembl)
+        } else {
+            None
+        }
+    }
+}
+
+/// Transcript IDs without version.
+pub type Transcripts = HashSet<String>;
+
+pub fn strip_version(id: &str) -> &str {
+    id.split_once('.').map_or(id, |(id, _)| id)
+}
+
+/// Read transcripts selected by policy: a list of IDs for canonical, the
+/// RefSeq and Ensembl IDs of MANE Select rows of `MANE.GRCh38.summary.txt`
+/// for mane.
+pub fn read_transcripts(path: &Path, policy: TranscriptPolicy) -> io::Result<Transcripts> {
+    let content = fs::read_to_string(path)?;
+    if policy != TranscriptPolicy::Mane {
+        return Ok(content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| strip_version(line).to_string())
+            .collect());
+    }
+
+    let mut lines = content.lines();
+    let header: Vec<&str> = lines
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches('#')
+        .split('\t')
+        .collect();
+    let column = |name: &str| {
+        header.iter().position(|x| *x == name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("no {} column in MANE summary {:?}", name, path),
+            )
+        })
+    };
+    let (refseq, ensembl, status) = (
+        column("RefSeq_nuc")?,
+        column("Ensembl_nuc")?,
+        column("MANE_status")?,
+    );
+
+    let mut transcripts = HashSet::new();
+    for line in lines {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.get(status) != Some(&"MANE Select") {
+            continue;
+        }
+        for i in [refseq, ensembl] {
+            if let Some(id) = fields.get(i).filter(|x| !x.is_empty()) {
+                transcripts.insert(strip_version(id).to_string());
+            }
+        }
+    }
+    Ok(transcripts)
+}
+
+/// Leave out ANN entries of transcripts of the other namespace for alleles
+/// having entries of transcripts of source.
+pub fn prefer_source(
+    entries: Vec<Vec<String>>,
+    ann: &AnnFields,
+    source: TranscriptSource,
+) -> Vec<Vec<String>> {
+    let field = |entry: &Vec<String>, i: usize| entry.get(i).cloned().unwrap_or_default();
+    let preferred: HashSet<String> = entries
+        .iter()
+        .filter(|x| TranscriptSource::of(&field(x, ann.feature_id)) == Some(source))
+        .map(|x| field(x, ann.allele))
+        .collect();
+    entries
+        .into_iter()
+        .filter(|x| {
+            !preferred.contains(&field(x, ann.allele))
+                || TranscriptSource::of(&field(x, ann.feature_id)).map_or(true, |s| s == source)
+        })
+        .collect()
+}
+
+/// Keep one ANN entry per allele, unless every entry is kept. Alleles
+/// without an entry of a listed transcript keep their worst entry.
+pub fn select(
+    entries: Vec<Vec<String>>,
+    ann: &AnnFields,
+    policy: TranscriptPolicy,
+    transcripts: &Transcripts,
+) -> Vec<Vec<String>> {
+    if policy == TranscriptPolicy::All {
+        return entries;
+    }
+    let field = |entry: &Vec<String>, i: usize| entry.get(i).cloned().unwrap_or_default();
+
+    let mut alleles: Vec<String> = Vec::new();
+    for entry in &entries {
+        let allele = field(entry, ann.allele);
+        if !alleles.contains(&allele) {
+            alleles.push(allele);
+        }
+    }
+
+    alleles
+        .iter()
+        .filter_map(|allele| {
+            let candidates = entries.iter().filter(|x| &field(x, ann.allele) == allele);
+            let listed = candidates.clone().find(|x| {
+                policy != TranscriptPolicy::Worst
+                    && transcripts.contains(strip_version(&field(x, ann.feature_id)))
+            });
+            // SnpEff sorts entries by impact, so the first one wins ties.
+            let worst = candidates
+                .rev()
+                .max_by_key(|x| Impact::parse(&field(x, ann.impact)));
+            listed.or(worst).cloned()
+        })
+        .collect()
+}
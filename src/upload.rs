@@ -0,0 +1,659 @@
+use crate::auth::Auth;
+use crate::checkpoint::{Checkpoint, Position};
+use crate::elasticsearch::Elasticsearch;
+use crate::exit::Exit;
+use crate::kafka::Kafka;
+use crate::ledger::{Entry, Ledger};
+use crate::mongodb::MongoDb;
+use crate::progress::Progress;
+use clap::ArgEnum;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::{info, warn};
+use rand::Rng;
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{CONTENT_ENCODING, CONTENT_TYPE};
+use reqwest::{Method, StatusCode};
+use serde::Serialize;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::slice;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// A variant already serialized in the wire format.
+pub type Payload = Vec<u8>;
+
+/// Encoding of the variants sent to the server.
+#[derive(ArgEnum, Clone, Copy, PartialEq)]
+pub enum WireFormat {
+    Json,
+    /// MessagePack, with fields named like in JSON.
+    Msgpack,
+    Cbor,
+}
+
+impl WireFormat {
+    fn content_type(&self) -> &'static str {
+        match self {
+            WireFormat::Json => "application/json",
+            WireFormat::Msgpack => "application/msgpack",
+            WireFormat::Cbor => "application/cbor",
+        }
+    }
+
+    pub fn encode<T: Serialize>(&self, value: &T) -> Payload {
+        match self {
+            WireFormat::Json => serde_json::to_vec(value).unwrap(),
+            WireFormat::Msgpack => rmp_serde::to_vec_named(value).unwrap(),
+            WireFormat::Cbor => {
+                let mut payload = Vec::new();
+                ciborium::ser::into_writer(value, &mut payload).unwrap();
+                payload
+            }
+        }
+    }
+
+    /// Re-encode a JSON payload, e.g. a line of an NDJSON file.
+    pub fn transcode(&self, json: Payload) -> Payload {
+        match self {
+            WireFormat::Json => json,
+            format => format.encode(&serde_json::from_slice::<Value>(&json).unwrap()),
+        }
+    }
+
+    /// Re-encode a payload as JSON, for servers that don't accept this
+    /// format.
+    pub fn json(&self, payload: &[u8]) -> Payload {
+        let value: Value = match self {
+            WireFormat::Json => return payload.to_vec(),
+            WireFormat::Msgpack => rmp_serde::from_slice(payload).unwrap(),
+            WireFormat::Cbor => ciborium::de::from_reader(payload).unwrap(),
+        };
+        serde_json::to_vec(&value).unwrap()
+    }
+
+    /// Body of a request with a single payload or an array of them.
+    fn body<'a>(&self, payloads: &'a [Payload], batch: bool) -> Cow<'a, [u8]> {
+        if batch {
+            Cow::Owned(self.array(payloads))
+        } else {
+            Cow::Borrowed(&payloads[0])
+        }
+    }
+
+    /// Array of payloads, each of which is already encoded.
+    fn array(&self, payloads: &[Payload]) -> Vec<u8> {
+        let size = payloads.iter().map(|p| p.len() + 1).sum::<usize>() + 9;
+        let mut body = Vec::with_capacity(size);
+        let len = payloads.len();
+        match self {
+            WireFormat::Json => return json_array(payloads),
+            WireFormat::Msgpack if len < 16 => body.push(0x90 | len as u8),
+            WireFormat::Msgpack if len <= u16::MAX as usize => {
+                body.push(0xdc);
+                body.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            WireFormat::Msgpack => {
+                body.push(0xdd);
+                body.extend_from_slice(&(len as u32).to_be_bytes());
+            }
+            WireFormat::Cbor if len < 24 => body.push(0x80 | len as u8),
+            WireFormat::Cbor if len <= u16::MAX as usize => {
+                body.push(0x99);
+                body.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            WireFormat::Cbor => {
+                body.push(0x9a);
+                body.extend_from_slice(&(len as u32).to_be_bytes());
+            }
+        }
+        payloads.iter().for_each(|p| body.extend_from_slice(p));
+        body
+    }
+}
+
+/// Message for a failed request, telling which option to raise when the
+/// server is too slow.
+pub fn request_error(e: &reqwest::Error) -> String {
+    if e.is_connect() && e.is_timeout() {
+        format!(
+            "Timed out connecting to server, see --connect-timeout: {}",
+            e
+        )
+    } else if e.is_timeout() {
+        format!("Timed out waiting for server, see --request-timeout: {}", e)
+    } else {
+        format!("Error sending request: {}", e)
+    }
+}
+
+/// Longest delay between two attempts, however many retries are allowed.
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// How often and how long to wait before resending a failed request.
+pub struct RetryPolicy {
+    pub retries: u32,
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Exponential backoff with jitter, so that concurrent workers don't
+    /// retry in lockstep. The delay stops doubling at MAX_BACKOFF.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let base = self.backoff.as_secs_f64() * 2f64.powi(attempt.min(32) as i32);
+        let base = base.min(MAX_BACKOFF.as_secs_f64());
+        Duration::from_secs_f64(base * rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+/// Token bucket shared by every worker, refilled at rate tokens per second
+/// and holding at most one second worth of tokens. Clones share the bucket.
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    bucket: Option<Arc<Mutex<Bucket>>>,
+}
+
+struct Bucket {
+    rate: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    /// Limiter allowing rate requests per second, or any number if None.
+    pub fn new(rate: Option<f64>) -> Self {
+        let bucket = rate.map(|rate| {
+            Arc::new(Mutex::new(Bucket {
+                rate,
+                tokens: rate.max(1.0),
+                updated: Instant::now(),
+            }))
+        });
+        RateLimiter { bucket }
+    }
+
+    /// Wait until a request can be sent.
+    fn acquire(&self) {
+        let bucket = match &self.bucket {
+            Some(bucket) => bucket,
+            None => return,
+        };
+        let wait = {
+            let mut bucket = bucket.lock().unwrap();
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * bucket.rate).min(bucket.rate.max(1.0));
+            bucket.updated = now;
+            // Tokens may go negative, so waiting requests queue up behind
+            // each other instead of all waking at once.
+            bucket.tokens -= 1.0;
+            (-bucket.tokens / bucket.rate).max(0.0)
+        };
+        thread::sleep(Duration::from_secs_f64(wait));
+    }
+}
+
+/// How variants are written to the server.
+#[derive(ArgEnum, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// POST new variants, failing on variants already in the dataset.
+    Create,
+    /// PUT variants, replacing those with the same dataset, assembly,
+    /// position, REF and ALT, so imports can be run again.
+    Upsert,
+}
+
+impl Mode {
+    fn method(&self) -> Method {
+        match self {
+            Mode::Create => Method::POST,
+            Mode::Upsert => Method::PUT,
+        }
+    }
+}
+
+pub struct Uploader {
+    client: Client,
+    url: String,
+    batch_url: String,
+    auth: Auth,
+    retry: RetryPolicy,
+    limiter: RateLimiter,
+    compress: bool,
+    mode: Mode,
+    format: WireFormat,
+    progress: Progress,
+    batch_supported: AtomicBool,
+    format_supported: AtomicBool,
+}
+
+impl Uploader {
+    pub fn new(
+        client: Client,
+        host: &str,
+        auth: Auth,
+        retry: RetryPolicy,
+        limiter: RateLimiter,
+        compress: bool,
+        progress: Progress,
+    ) -> Self {
+        Uploader {
+            client,
+            url: format!("{}/variants", host),
+            batch_url: format!("{}/variants/batch", host),
+            auth,
+            retry,
+            limiter,
+            compress,
+            mode: Mode::Create,
+            format: WireFormat::Json,
+            progress,
+            batch_supported: AtomicBool::new(true),
+            format_supported: AtomicBool::new(true),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_format(mut self, format: WireFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Send payloads to url, as an array if batch. Servers that respond that
+    /// they don't accept the wire format are detected on the first attempt
+    /// and every following payload is re-encoded as JSON.
+    fn negotiate(&self, url: &str, payloads: &[Payload], batch: bool) -> Response {
+        if self.format_supported.load(Ordering::Relaxed) {
+            let res = self.send(url, &self.format.body(payloads, batch), self.format);
+            if res.status() != StatusCode::UNSUPPORTED_MEDIA_TYPE || self.format == WireFormat::Json
+            {
+                return res;
+            }
+            if self.format_supported.swap(false, Ordering::Relaxed) {
+                warn!(
+                    "Server does not accept {}, sending JSON",
+                    self.format.content_type()
+                );
+            }
+        }
+        let payloads: Vec<Payload> = payloads.iter().map(|p| self.format.json(p)).collect();
+        self.send(
+            url,
+            &WireFormat::Json.body(&payloads, batch),
+            WireFormat::Json,
+        )
+    }
+
+    /// Send every variant individually, for servers known not to have the
+    /// bulk endpoint.
+    pub fn disable_batch(&self) {
+        self.batch_supported.store(false, Ordering::Relaxed);
+    }
+
+    /// Send body to url, retrying on connection errors and 5xx responses.
+    /// The last response is returned once retries are exhausted.
+    fn send(&self, url: &str, body: &[u8], format: WireFormat) -> Response {
+        let body = if self.compress {
+            gzip(body)
+        } else {
+            body.to_vec()
+        };
+
+        let mut attempt = 0;
+        loop {
+            self.limiter.acquire();
+            let mut req = self
+                .auth
+                .apply(self.client.request(self.mode.method(), url))
+                .header(CONTENT_TYPE, format.content_type());
+            if self.compress {
+                req = req.header(CONTENT_ENCODING, "gzip");
+            }
+            let result = req.body(body.clone()).send();
+            if let Ok(res) = &result {
+                self.progress.response(res.status().as_u16());
+            }
+
+            if result
+                .as_ref()
+                .is_ok_and(|res| !res.status().is_server_error())
+            {
+                return result.unwrap();
+            }
+            self.progress.failed();
+
+            match result {
+                Ok(res) if attempt >= self.retry.retries => return res,
+                Err(e) if attempt >= self.retry.retries => {
+                    Exit::for_error(&e).fail(request_error(&e))
+                }
+                Ok(res) => warn!("Server responded with {}, retrying", res.status()),
+                Err(e) => warn!("{}, retrying", request_error(&e)),
+            }
+
+            thread::sleep(self.retry.delay(attempt));
+            attempt += 1;
+        }
+    }
+
+    pub fn post(&self, payload: &Payload) {
+        let res = self.negotiate(&self.url, slice::from_ref(payload), false);
+        match self.mode {
+            Mode::Create if res.status() != StatusCode::CREATED => rejected(res),
+            Mode::Upsert if !res.status().is_success() => rejected(res),
+            _ => {}
+        }
+        self.progress.uploaded(1);
+    }
+
+    /// Send variants as an array to the bulk endpoint. Servers without
+    /// the bulk endpoint are detected on the first attempt and every
+    /// following variant is sent individually.
+    pub fn post_batch(&self, payloads: &[Payload]) {
+        if payloads.len() == 1 || !self.batch_supported.load(Ordering::Relaxed) {
+            payloads.iter().for_each(|p| self.post(p));
+            return;
+        }
+
+        let res = self.negotiate(&self.batch_url, payloads, true);
+
+        match res.status() {
+            StatusCode::NOT_FOUND
+            | StatusCode::METHOD_NOT_ALLOWED
+            | StatusCode::NOT_IMPLEMENTED => {
+                if self.batch_supported.swap(false, Ordering::Relaxed) {
+                    warn!("Server does not support batch upload, sending variants one by one");
+                }
+                payloads.iter().for_each(|p| self.post(p));
+            }
+            status if status.is_success() => self.progress.uploaded(payloads.len() as u64),
+            _ => rejected(res),
+        }
+    }
+}
+
+/// Exit with the response of a server rejecting variants.
+pub fn rejected(res: Response) -> ! {
+    let status = res.status();
+    let body = res.text().unwrap_or_default();
+    Exit::for_status(status).fail(format!("Server responded with {}: {}", status, body))
+}
+
+/// Destination of the variants.
+pub enum Sink {
+    Server(Uploader),
+    Kafka(Kafka),
+    Elasticsearch(Elasticsearch),
+    MongoDb(MongoDb),
+}
+
+impl Sink {
+    fn send(&self, batch: &Batch) {
+        match self {
+            Sink::Server(uploader) => uploader.post_batch(&batch.payloads),
+            Sink::Kafka(kafka) => kafka.send(&batch.keys, &batch.payloads),
+            Sink::Elasticsearch(elasticsearch) => elasticsearch.send(&batch.payloads),
+            Sink::MongoDb(mongodb) => mongodb.send(&batch.payloads),
+        }
+    }
+}
+
+fn gzip(body: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::with_capacity(body.len() / 4), Compression::default());
+    encoder.write_all(body).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn json_array(payloads: &[Payload]) -> Vec<u8> {
+    let size = payloads.iter().map(|p| p.len() + 1).sum::<usize>() + 1;
+    let mut body = Vec::with_capacity(size);
+    body.push(b'[');
+    for (i, payload) in payloads.iter().enumerate() {
+        if i > 0 {
+            body.push(b',');
+        }
+        body.extend_from_slice(payload);
+    }
+    body.push(b']');
+    body
+}
+
+/// Serialized variants sent together, with their message keys, their
+/// number per contig and the position of the last one.
+struct Batch {
+    payloads: Vec<Payload>,
+    keys: Vec<Option<String>>,
+    contigs: BTreeMap<String, u64>,
+    entries: Vec<Entry>,
+    position: Position,
+}
+
+/// Pool of threads sending batches of serialized variants to the sink.
+/// Batches are handed over through a bounded channel, so parsing never gets
+/// too far ahead of the uploads.
+pub struct WorkerPool {
+    sender: SyncSender<(u64, Batch)>,
+    handles: Vec<JoinHandle<()>>,
+    submitted: u64,
+    batch_size: usize,
+    payloads: Vec<Payload>,
+    keys: Vec<Option<String>>,
+    contigs: BTreeMap<String, u64>,
+    entries: Vec<Entry>,
+    position: Option<Position>,
+    ledger: Option<Arc<Ledger>>,
+    skip_imported: bool,
+    already_imported: u64,
+}
+
+impl WorkerPool {
+    pub fn new(
+        sink: Sink,
+        progress: Progress,
+        workers: usize,
+        batch_size: usize,
+        checkpoint: Option<Checkpoint>,
+        ledger: Option<Ledger>,
+        skip_imported: bool,
+    ) -> Self {
+        let workers = workers.max(1);
+        let (sender, receiver) = sync_channel(workers * 2);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let sink = Arc::new(sink);
+        let checkpoint = Arc::new(checkpoint);
+        let ledger = ledger.map(Arc::new);
+
+        let handles = (0..workers)
+            .map(|_| {
+                let receiver = Arc::clone(&receiver);
+                let sink = Arc::clone(&sink);
+                let checkpoint = Arc::clone(&checkpoint);
+                let ledger = ledger.clone();
+                let progress = progress.clone();
+                thread::spawn(move || {
+                    work(
+                        &sink,
+                        &progress,
+                        &receiver,
+                        checkpoint.as_ref().as_ref(),
+                        ledger.as_deref(),
+                    )
+                })
+            })
+            .collect();
+
+        let batch_size = batch_size.max(1);
+        WorkerPool {
+            sender,
+            handles,
+            submitted: 0,
+            batch_size,
+            payloads: Vec::with_capacity(batch_size),
+            keys: Vec::with_capacity(batch_size),
+            contigs: BTreeMap::new(),
+            entries: Vec::new(),
+            position: None,
+            ledger,
+            skip_imported,
+            already_imported: 0,
+        }
+    }
+
+    /// Add a variant to the current batch, queueing the batch for upload
+    /// once it is full. Blocks while all workers are busy and returns false
+    /// if every worker has stopped. With --skip-already-imported, variants
+    /// the ledger has with the same payload are left out. The key is only
+    /// used by Kafka.
+    pub fn push(
+        &mut self,
+        position: Position,
+        key: Option<String>,
+        entry: Option<Entry>,
+        payload: Payload,
+    ) -> bool {
+        if let (Some(ledger), Some(entry)) = (&self.ledger, entry) {
+            let imported = self.skip_imported
+                && ledger
+                    .contains(&entry)
+                    .unwrap_or_else(|e| panic!("Error reading ledger: {}", e));
+            if imported {
+                self.already_imported += 1;
+                self.position = Some(position);
+                return true;
+            }
+            self.entries.push(entry);
+        }
+        match self.contigs.get_mut(&position.reference_name) {
+            Some(n) => *n += 1,
+            None => {
+                self.contigs.insert(position.reference_name.clone(), 1);
+            }
+        }
+        self.position = Some(position);
+        self.payloads.push(payload);
+        self.keys.push(key);
+        if self.payloads.len() < self.batch_size {
+            return true;
+        }
+        self.submit()
+    }
+
+    fn submit(&mut self) -> bool {
+        // Workers only stop before join when an upload failed.
+        if self.handles.iter().any(|handle| handle.is_finished()) {
+            return false;
+        }
+        let batch = Batch {
+            payloads: std::mem::replace(&mut self.payloads, Vec::with_capacity(self.batch_size)),
+            keys: std::mem::replace(&mut self.keys, Vec::with_capacity(self.batch_size)),
+            contigs: std::mem::take(&mut self.contigs),
+            entries: std::mem::take(&mut self.entries),
+            position: self.position.take().unwrap(),
+        };
+        let seq = self.submitted;
+        self.submitted += 1;
+        self.sender.send((seq, batch)).is_ok()
+    }
+
+    /// Queue the last, partial batch and wait for every batch to be uploaded.
+    /// Returns the number of variants skipped as already imported, and the
+    /// panic of the first worker that failed, to raise again once the
+    /// summary is written.
+    pub fn join(mut self) -> (u64, thread::Result<()>) {
+        // A batch without payloads still advances the checkpoint past the
+        // variants skipped as already imported.
+        if self.position.is_some() {
+            self.submit();
+        }
+        drop(self.sender);
+        let mut result = Ok(());
+        for handle in self.handles {
+            let joined = handle.join();
+            if result.is_ok() {
+                result = joined;
+            }
+        }
+        if self.already_imported > 0 {
+            info!(
+                "Skipped {} variants already imported according to the ledger",
+                self.already_imported
+            );
+        }
+        (self.already_imported, result)
+    }
+}
+
+fn work(
+    sink: &Sink,
+    progress: &Progress,
+    receiver: &Mutex<Receiver<(u64, Batch)>>,
+    checkpoint: Option<&Checkpoint>,
+    ledger: Option<&Ledger>,
+) {
+    loop {
+        let (seq, batch) = match receiver.lock().unwrap().recv() {
+            Ok(message) => message,
+            Err(_) => return,
+        };
+        if !batch.payloads.is_empty() {
+            sink.send(&batch);
+            progress.uploaded_contigs(&batch.contigs);
+        }
+        if let Some(ledger) = ledger {
+            ledger
+                .record(&batch.entries)
+                .unwrap_or_else(|e| panic!("Error writing ledger: {}", e));
+        }
+        if let Some(checkpoint) = checkpoint {
+            checkpoint.complete(seq, batch.position);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lengths around every change of the array header.
+    const LENGTHS: [usize; 8] = [0, 15, 16, 23, 24, 255, 65535, 65536];
+
+    fn array(format: WireFormat, len: usize) -> Vec<u8> {
+        let payloads: Vec<Payload> = (0..len as u32).map(|i| format.encode(&i)).collect();
+        format.array(&payloads)
+    }
+
+    #[test]
+    fn msgpack_arrays_decode_to_the_payloads() {
+        for len in LENGTHS {
+            let body = array(WireFormat::Msgpack, len);
+            let values: Vec<u32> = rmp_serde::from_slice(&body).unwrap();
+            assert_eq!(values, (0..len as u32).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn cbor_arrays_decode_to_the_payloads() {
+        for len in LENGTHS {
+            let body = array(WireFormat::Cbor, len);
+            let values: Vec<u32> = ciborium::de::from_reader(&body[..]).unwrap();
+            assert_eq!(values, (0..len as u32).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn json_arrays_decode_to_the_payloads() {
+        let body = array(WireFormat::Json, 3);
+        let values: Vec<u32> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(values, vec![0, 1, 2]);
+    }
+}
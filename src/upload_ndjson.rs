@@ -0,0 +1,91 @@
+use crate::checkpoint::Position;
+use crate::cli::UploadNdjsonOpts;
+use crate::ledger::Entry;
+use crate::ndjson;
+use crate::progress::Progress;
+use log::{info, warn};
+use serde::Deserialize;
+use std::io::BufRead;
+use std::panic;
+
+/// Just enough of a variant to report its position and record it in the
+/// ledger.
+#[derive(Deserialize)]
+struct Locus {
+    #[serde(rename = "referenceName")]
+    reference_name: String,
+    start: i64,
+    id: Option<String>,
+    #[serde(rename = "datasetId")]
+    dataset_id: Option<String>,
+}
+
+pub fn run(opts: UploadNdjsonOpts) {
+    let path = opts.ndjson_file;
+    let input = ndjson::open(&path).unwrap_or_else(|e| panic!("Error opening {:?}: {}", path, e));
+
+    let resume_from = opts.upload.resume_position();
+    let mut pool = opts
+        .upload
+        .start(&opts.server, opts.upload.rate_limiter(), Progress::hidden());
+
+    let mut total_variants: u32 = 0;
+    let mut skipped_variants: u32 = 0;
+
+    for (i, line) in input.lines().enumerate() {
+        let line = line.unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e));
+        let record = i as u64 + 1;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let locus: Locus = match serde_json::from_str(&line) {
+            Ok(locus) => locus,
+            Err(e) => {
+                warn!("Skipping line {}: {}", record, e);
+                skipped_variants += 1;
+                continue;
+            }
+        };
+
+        total_variants += 1;
+
+        if let Some(position) = &resume_from {
+            if record == position.record {
+                assert!(
+                    locus.reference_name == position.reference_name
+                        && locus.start == position.start,
+                    "Checkpoint does not match {:?} at line {}",
+                    path,
+                    record
+                );
+            }
+            if record <= position.record {
+                continue;
+            }
+        }
+
+        let position = Position {
+            record,
+            reference_name: locus.reference_name,
+            start: locus.start,
+        };
+        let payload = opts.upload.wire_format.transcode(line.into_bytes());
+        let key = opts.upload.message_key(locus.id.as_deref(), &position);
+        let entry = match (&opts.upload.ledger, locus.id, locus.dataset_id) {
+            (Some(_), Some(id), Some(dataset)) => Some(Entry::new(id, dataset, &payload)),
+            _ => None,
+        };
+        if !pool.push(position, key, entry, payload) {
+            break;
+        }
+    }
+
+    if let (_, Err(payload)) = pool.join() {
+        panic::resume_unwind(payload);
+    }
+
+    info!("Total variants: {}", total_variants);
+    info!("Skipped variants: {}", skipped_variants);
+}
@@ -0,0 +1,17 @@
+use crate::cli::ValidateOpts;
+use crate::exit::Exit;
+use crate::reader::VariantReader;
+
+pub fn run(opts: ValidateOpts) {
+    let mut reader = VariantReader::new(&opts.input, String::new(), String::new());
+
+    reader.by_ref().for_each(drop);
+
+    println!("{}", reader.summary());
+    if reader.counts.skipped > 0 {
+        Exit::ValidationFailed.fail(format!(
+            "{} records can't be converted",
+            reader.counts.skipped
+        ));
+    }
+}
@@ -0,0 +1,1131 @@
+use crate::clinvar;
+use crate::error::{Error, Result};
+use crate::gnomad::Gnomad;
+use crate::hwe;
+use crate::mapping::Mapping;
+use crate::prediction::{self, CsqFields, Prediction};
+use crate::samples;
+use crate::transcript::{self, TranscriptPolicy, TranscriptSource, Transcripts};
+use clap::ArgEnum;
+use log::{debug, warn};
+use rust_htslib::bcf::header::TagType;
+use rust_htslib::bcf::header::{HeaderRecord, HeaderView};
+use rust_htslib::bcf::record::Numeric;
+use rust_htslib::bcf::Record;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use statrs::statistics::{Data, Distribution, Max, Min, OrderStatistics};
+use std::collections::{BTreeMap, HashMap};
+use std::str;
+
+/// Symbolic alleles of gVCF files standing for any other allele.
+pub const NON_REF: [&str; 2] = ["<NON_REF>", "<*>"];
+pub const NS: &str = "NS";
+const GT: &str = "GT";
+const MIN_DP: &str = "MIN_DP";
+/// htslib's padding of FORMAT vectors shorter than the longest one, such as
+/// haploid genotypes among diploid ones.
+pub const VECTOR_END: i32 = i32::MIN + 1;
+pub const VECTOR_END_FLOAT: u32 = 0x7F80_0002;
+
+/// How the values of a sample are summarized in FORMAT distributions, for
+/// fields with several values per sample such as AD.
+#[derive(ArgEnum, Clone, Copy, PartialEq)]
+pub enum Aggregation {
+    All,
+    First,
+    Sum,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct FormatDistribution {
+    min: f64,
+    q25: f64,
+    median: f64,
+    q75: f64,
+    max: f64,
+    mean: f64,
+    /// Sample standard deviation, None for a single value.
+    sd: Option<f64>,
+    /// Percentiles given with --percentiles, keyed as `p5`, `p95`...
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    percentiles: BTreeMap<String, f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    histogram: Option<Histogram>,
+}
+
+/// Number of values from every edge up to the next one, the last bin having
+/// no upper bound. Values below the first edge are not counted.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct Histogram {
+    edges: Vec<f64>,
+    counts: Vec<u32>,
+}
+
+impl Histogram {
+    fn new(edges: &[f64], values: &[f64]) -> Self {
+        let mut counts = vec![0; edges.len()];
+        for value in values {
+            let bin = edges.partition_point(|edge| edge <= value);
+            if let Some(count) = bin.checked_sub(1).and_then(|i| counts.get_mut(i)) {
+                *count += 1;
+            }
+        }
+        Histogram {
+            edges: edges.to_vec(),
+            counts,
+        }
+    }
+}
+
+/// Number of samples homozygous for REF, heterozygous and homozygous for an
+/// ALT allele.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default)]
+pub struct GenotypeCounts {
+    #[serde(rename = "homRef")]
+    pub hom_ref: u32,
+    pub het: u32,
+    #[serde(rename = "homAlt")]
+    pub hom_alt: u32,
+    /// P-value of the Hardy-Weinberg exact test.
+    #[serde(rename = "hwePValue")]
+    pub hwe_p_value: f64,
+    #[serde(rename = "inbreedingCoefficient")]
+    pub inbreeding_coefficient: Option<f64>,
+}
+
+impl GenotypeCounts {
+    pub fn set_hwe(&mut self) {
+        self.hwe_p_value = hwe::exact_test(self.het, self.hom_ref, self.hom_alt);
+        self.inbreeding_coefficient =
+            hwe::inbreeding_coefficient(self.het, self.hom_ref, self.hom_alt);
+    }
+}
+
+/// Other end of a breakend (BND) record, from ALT alleles such as
+/// `N[chr5:123[`. The orientation is kept in the ALT allele.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct Mate {
+    #[serde(rename = "referenceName")]
+    pub reference_name: String,
+    pub start: i64,
+    /// INFO/MATEID, the ID of the mate record.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct Variant {
+    /// Hash of the dataset, assembly, position and alleles, the same on
+    /// every import of the variant.
+    pub id: Option<String>,
+    #[serde(rename = "datasetId")]
+    pub dataset_id: String,
+    #[serde(rename = "totalSamples")]
+    pub total_samples: u32,
+    #[serde(rename = "assemblyId")]
+    pub assembly_id: String,
+    #[serde(rename = "snpIds")]
+    pub snp_ids: Option<Vec<String>>,
+    #[serde(rename = "referenceName")]
+    pub reference_name: String,
+    pub start: i64,
+    #[serde(rename = "referenceBases")]
+    pub reference_bases: String,
+    #[serde(rename = "alternateBases")]
+    pub alternate_bases: Vec<String>,
+    /// GA4GH VRS identifiers of the ALT alleles, with --vrs.
+    #[serde(rename = "vrsIds", default, skip_serializing_if = "Vec::is_empty")]
+    pub vrs_ids: Vec<String>,
+    /// Canonical SPDI of the ALT alleles, with --spdi.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spdi: Vec<String>,
+    #[serde(rename = "geneSymbol")]
+    pub gene_symbol: Option<Vec<String>>,
+    #[serde(rename = "alleleFrequency")]
+    pub allele_frequency: Vec<f32>,
+    #[serde(rename = "alleleCount", skip_serializing_if = "Option::is_none")]
+    pub allele_count: Option<Vec<u32>>,
+    #[serde(rename = "alleleNumber", skip_serializing_if = "Option::is_none")]
+    pub allele_number: Option<u32>,
+    /// Sample counts of every ALT allele, from FORMAT/GT.
+    #[serde(rename = "genotypeCounts", skip_serializing_if = "Option::is_none")]
+    pub genotype_counts: Option<Vec<GenotypeCounts>>,
+    /// Fraction of samples without missing alleles in FORMAT/GT.
+    #[serde(rename = "callRate", skip_serializing_if = "Option::is_none")]
+    pub call_rate: Option<f64>,
+    #[serde(rename = "sampleCount")]
+    pub sample_count: Option<i32>,
+    pub coverage: Option<FormatDistribution>,
+    #[serde(rename = "genotypeQuality")]
+    pub genotype_quality: Option<FormatDistribution>,
+    #[serde(
+        rename = "formatDistributions",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub format_distributions: BTreeMap<String, FormatDistribution>,
+    /// In silico predictions read from the INFO tags given with --score.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub scores: BTreeMap<String, f64>,
+    /// Fields declared in the --mapping file.
+    #[serde(flatten)]
+    pub mapped: BTreeMap<String, serde_json::Value>,
+    /// SIFT and PolyPhen predictions from VEP or dbNSFP annotations.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sift: Option<Prediction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub polyphen: Option<Prediction>,
+    pub clnsig: Option<String>,
+    /// Significance of each submission when they conflict.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clnsigconf: Option<String>,
+    /// ClinVar review status.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clnrevstat: Option<String>,
+    /// ClinVar variation ID, when looked up with --clinvar.
+    #[serde(rename = "clinvarId", skip_serializing_if = "Option::is_none")]
+    pub clinvar_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gnomad: Option<Gnomad>,
+    pub hgvs: Option<Vec<String>>,
+    #[serde(rename = "type")]
+    pub variant_type: Option<Vec<String>>,
+    #[serde(rename = "svType", skip_serializing_if = "Option::is_none")]
+    pub sv_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<i64>,
+    #[serde(rename = "svLength", skip_serializing_if = "Option::is_none")]
+    pub sv_length: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mate: Option<Mate>,
+    /// Allele of every ANN entry, matching geneSymbol, type and hgvs.
+    #[serde(
+        rename = "annotationAlleles",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub annotation_alleles: Vec<String>,
+}
+
+/// Positions of the ANN sub-fields used in variants, read from the
+/// description of ANN in the header.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AnnFields {
+    pub allele: usize,
+    pub consequence: usize,
+    pub impact: usize,
+    pub gene_symbol: usize,
+    pub variant_type: usize,
+    pub feature_id: usize,
+    pub hgvs: usize,
+}
+
+impl Default for AnnFields {
+    /// Positions of SnpEff 4 and ClinEff.
+    fn default() -> Self {
+        AnnFields {
+            allele: 0,
+            consequence: 1,
+            impact: 2,
+            gene_symbol: 3,
+            variant_type: 5,
+            feature_id: 6,
+            hgvs: 9,
+        }
+    }
+}
+
+impl AnnFields {
+    /// Find sub-fields by name in a description such as `Functional
+    /// annotations: 'Allele | Annotation | ...'`, keeping default positions
+    /// of those not found.
+    pub fn from_header(header: &HeaderView) -> Self {
+        let mut fields = AnnFields::default();
+        let names = match subfield_names(header, "ANN") {
+            Some(names) => names,
+            None => return fields,
+        };
+
+        let find = |name: &str, index: &mut usize| match names.iter().position(|x| x == name) {
+            Some(i) => *index = i,
+            None => warn!("ANN header has no {} field, using position {}", name, index),
+        };
+        find("allele", &mut fields.allele);
+        find("annotation", &mut fields.consequence);
+        find("annotation_impact", &mut fields.impact);
+        find("gene_name", &mut fields.gene_symbol);
+        find("feature_type", &mut fields.variant_type);
+        find("feature_id", &mut fields.feature_id);
+        find("hgvs.c", &mut fields.hgvs);
+        fields
+    }
+}
+
+/// Lowercase names of the sub-fields of an INFO field separated by `|`,
+/// listed in its description after a colon and optionally quoted, like
+/// `Functional annotations: 'Allele | Annotation | ...'` or `Consequence
+/// annotations from Ensembl VEP. Format: Allele|Consequence|...`.
+pub fn subfield_names(header: &HeaderView, tag: &str) -> Option<Vec<String>> {
+    let description = header.header_records().into_iter().find_map(|x| match x {
+        HeaderRecord::Info { values, .. } if values.get("ID").is_some_and(|id| id == tag) => {
+            values.get("Description").cloned()
+        }
+        _ => None,
+    })?;
+    let names = description.trim_matches('"');
+    let names = match (names.find('\''), names.rfind('\'')) {
+        (Some(start), Some(end)) if start < end => &names[start + 1..end],
+        _ => names.rsplit_once(':').map_or(names, |(_, x)| x),
+    };
+    Some(names.split('|').map(|x| x.trim().to_lowercase()).collect())
+}
+
+/// Values shared by every variant of an import.
+pub struct Context {
+    pub dataset_id: String,
+    pub assembly_id: String,
+    pub total_samples: u32,
+    pub has_ns: bool,
+    pub ann_fields: AnnFields,
+    pub csq_fields: CsqFields,
+    pub mapping: Mapping,
+    pub transcript_policy: TranscriptPolicy,
+    pub transcript_source: Option<TranscriptSource>,
+    /// Transcripts preferred by the canonical and mane policies.
+    pub transcripts: Transcripts,
+    /// FORMAT tags of the coverage and genotypeQuality distributions.
+    pub coverage_tag: String,
+    pub gq_tag: String,
+    /// Other numeric FORMAT tags to summarize.
+    pub distributions: Vec<String>,
+    /// INFO tags of pathogenicity scores.
+    pub scores: Vec<String>,
+    /// Histogram edges of the coverage and genotypeQuality distributions,
+    /// no histogram if empty.
+    pub coverage_bins: Vec<f64>,
+    pub gq_bins: Vec<f64>,
+    /// Extra percentiles of every FORMAT distribution.
+    pub percentiles: Vec<usize>,
+    /// Aggregation of FORMAT tags whose values are not all used.
+    pub aggregations: HashMap<String, Aggregation>,
+    /// Reject records relying on defaults, such as an empty allele
+    /// frequency when AF is missing.
+    pub strict: bool,
+    /// Samples used for statistics, in header order, or None for all. INFO
+    /// AF, AC, AN and NS describe every sample, so they are computed from
+    /// the genotypes instead.
+    pub samples: Option<Vec<bool>>,
+}
+
+impl Variant {
+    pub fn from_record(record: &Record, context: &Context) -> Result<Variant> {
+        let snp_ids = get_snp_ids(record)?;
+        let mask = context.samples.as_deref();
+        let subset = mask.is_some();
+        let genotypes = get_genotypes(record, mask)?;
+        let n_alleles = record.allele_count() as usize;
+        let counts = genotypes.as_deref().map(|x| allele_counts(x, n_alleles));
+        let genotype_counts = genotypes.as_deref().map(|x| genotype_counts(x, n_alleles));
+        let call_rate = genotypes.as_deref().and_then(call_rate);
+        let allele_frequency = match get_allele_frequency(record)?.filter(|_| !subset) {
+            Some(af) => af,
+            None => match counts.as_deref().and_then(genotype_frequency) {
+                Some(af) => af,
+                None if context.strict => return Err(Error::MissingInfo("AF")),
+                None => Vec::new(),
+            },
+        };
+        let allele_count = match get_info_integers(record, "AC")?.filter(|_| !subset) {
+            Some(ac) => Some(ac.into_iter().map(|x| x.max(0) as u32).collect()),
+            None => counts.as_ref().map(|x| x[1..].to_vec()),
+        };
+        // Number=A fields must have one value per ALT allele.
+        if !allele_frequency.is_empty() {
+            check_per_allele("AF", allele_frequency.len(), n_alleles.saturating_sub(1))?;
+        }
+        if let Some(ac) = &allele_count {
+            check_per_allele("AC", ac.len(), n_alleles.saturating_sub(1))?;
+        }
+        let allele_number = match get_info_integer(record, "AN")?.filter(|_| !subset) {
+            Some(an) => Some(an.max(0) as u32),
+            None => counts.as_ref().map(|x| x.iter().sum()),
+        };
+        let coverage = match calc_distribution(
+            record,
+            &context.coverage_tag,
+            &context.coverage_bins,
+            context,
+        ) {
+            // GATK gVCF records have the minimum depth of reference blocks.
+            Ok(None) | Err(_) if has_format(record, MIN_DP) => {
+                debug!(
+                    "Using {} instead of {} at {}:{}",
+                    MIN_DP,
+                    context.coverage_tag,
+                    get_reference_name(record),
+                    record.pos() + 1
+                );
+                calc_distribution(record, MIN_DP, &context.coverage_bins, context)?
+            }
+            result => result?,
+        };
+        let genotype_quality =
+            calc_distribution(record, &context.gq_tag, &context.gq_bins, context)?;
+        let format_distributions = format_distributions(record, context)?;
+        let scores = get_scores(record, &context.scores)?;
+        let mapped = context.mapping.values(record, mask)?;
+        let (sift, polyphen) = prediction::predictions(record, &context.csq_fields)?;
+        let start = record.pos() + 1;
+
+        let rid = record.rid().ok_or(Error::MissingChrom)?;
+        let reference_name = to_string(record.header().rid2name(rid)?, "CHROM")?;
+
+        let alleles = record.alleles();
+        let reference_bases = to_string(alleles.first().ok_or(Error::MissingRef)?, "REF")?;
+        let alternate_bases: Vec<String> = alleles
+            .iter()
+            .skip(1)
+            .map(|x| to_string(x, "ALT"))
+            .collect::<Result<_>>()?;
+
+        let clnsig = if has_info(record, "CLNSIG") {
+            get_info_field(record, "CLNSIG")?.map(|x| clinvar::normalize_significance(&x))
+        } else {
+            None
+        };
+        let clnsigconf = if has_info(record, "CLNSIGCONF") {
+            get_info_field(record, "CLNSIGCONF")?.map(|x| clinvar::normalize_significance(&x))
+        } else {
+            None
+        };
+        let clnrevstat = if has_info(record, "CLNREVSTAT") {
+            get_info_field(record, "CLNREVSTAT")?.map(|x| clinvar::normalize_review_status(&x))
+        } else {
+            None
+        };
+
+        let mate = match alternate_bases.iter().find(|x| is_breakend(x)) {
+            Some(alt) => {
+                let mut mate = parse_breakend(alt)?;
+                if has_info(record, "MATEID") {
+                    mate.id = get_info_field(record, "MATEID")?.and_then(|x| x.into_iter().next());
+                }
+                Some(mate)
+            }
+            None => None,
+        };
+
+        let sv_type = if has_info(record, "SVTYPE") {
+            get_info_field(record, "SVTYPE")?.and_then(|x| x.into_iter().next())
+        } else {
+            None
+        };
+        let end = get_info_integer(record, "END")?;
+        let sv_length = get_info_integer(record, "SVLEN")?;
+
+        let sample_count = if subset {
+            genotypes.as_deref().map(|x| {
+                x.iter()
+                    .filter(|genotype| genotype.iter().any(Option::is_some))
+                    .count() as i32
+            })
+        } else if context.has_ns {
+            record.info(NS.as_bytes()).integer()?.map(|x| x[0])
+        } else {
+            None
+        };
+
+        let (gene_symbol, variant_type, hgvs, annotation_alleles) =
+            if let Some(ann) = get_ann(record)? {
+                let mut fields: Vec<Vec<String>> = ann.iter().map(|x| split_ann(x)).collect();
+                if let Some(source) = context.transcript_source {
+                    fields = transcript::prefer_source(fields, &context.ann_fields, source);
+                }
+                let mut fields = transcript::select(
+                    fields,
+                    &context.ann_fields,
+                    context.transcript_policy,
+                    &context.transcripts,
+                );
+                // Order entries like ALT alleles, other alleles last.
+                fields.sort_by_key(|x| {
+                    let allele = x.get(context.ann_fields.allele).map_or("", String::as_str);
+                    alternate_bases
+                        .iter()
+                        .position(|alt| alt == allele)
+                        .unwrap_or(alternate_bases.len())
+                });
+                let gene_symbol = get_field(&fields, context.ann_fields.gene_symbol)?;
+                let variant_type = get_field(&fields, context.ann_fields.variant_type)?;
+                let hgvs = get_field(&fields, context.ann_fields.hgvs)?;
+                let alleles = get_field(&fields, context.ann_fields.allele)?;
+                (Some(gene_symbol), Some(variant_type), Some(hgvs), alleles)
+            } else {
+                (None, None, None, Vec::new())
+            };
+
+        Ok(Variant {
+            id: None,
+            dataset_id: context.dataset_id.to_string(),
+            total_samples: context.total_samples,
+            assembly_id: context.assembly_id.to_string(),
+            snp_ids,
+            reference_name,
+            start,
+            reference_bases,
+            alternate_bases,
+            vrs_ids: Vec::new(),
+            spdi: Vec::new(),
+            gene_symbol,
+            allele_frequency,
+            allele_count,
+            allele_number,
+            genotype_counts,
+            call_rate,
+            sample_count,
+            coverage,
+            genotype_quality,
+            format_distributions,
+            scores,
+            mapped,
+            sift,
+            polyphen,
+            clnsig,
+            clnsigconf,
+            clnrevstat,
+            clinvar_id: None,
+            gnomad: None,
+            hgvs,
+            variant_type,
+            sv_type,
+            end,
+            sv_length,
+            mate,
+            annotation_alleles,
+        })
+    }
+
+    /// Whether the variant is a gVCF reference block, whose only ALT allele
+    /// is `<NON_REF>` or `<*>`.
+    pub fn is_reference_block(&self) -> bool {
+        !self.alternate_bases.is_empty()
+            && self
+                .alternate_bases
+                .iter()
+                .all(|x| NON_REF.contains(&x.as_str()))
+    }
+
+    /// Whether the variant is a structural variant, with SVTYPE, a symbolic
+    /// ALT allele such as `<DEL>` or a breakend.
+    pub fn is_structural(&self) -> bool {
+        self.sv_type.is_some()
+            || self.mate.is_some()
+            || self.alternate_bases.iter().any(|x| x.starts_with('<'))
+    }
+
+    /// Remove an ALT allele with its AF value and ANN entries. Returns
+    /// whether the variant had it.
+    pub fn remove_allele(&mut self, allele: &str) -> bool {
+        let index = match self.alternate_bases.iter().position(|x| x == allele) {
+            Some(index) => index,
+            None => return false,
+        };
+        if self.allele_frequency.len() == self.alternate_bases.len() {
+            self.allele_frequency.remove(index);
+        }
+        if let Some(ac) = &mut self.allele_count {
+            if ac.len() == self.alternate_bases.len() {
+                ac.remove(index);
+            }
+        }
+        if let Some(gc) = &mut self.genotype_counts {
+            if gc.len() == self.alternate_bases.len() {
+                gc.remove(index);
+            }
+        }
+        if self.vrs_ids.len() == self.alternate_bases.len() {
+            self.vrs_ids.remove(index);
+        }
+        if self.spdi.len() == self.alternate_bases.len() {
+            self.spdi.remove(index);
+        }
+        self.alternate_bases.remove(index);
+
+        let keep: Vec<bool> = self
+            .annotation_alleles
+            .iter()
+            .map(|x| x != allele)
+            .collect();
+        for values in [
+            &mut self.gene_symbol,
+            &mut self.variant_type,
+            &mut self.hgvs,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            let mut flags = keep.iter();
+            values.retain(|_| *flags.next().unwrap_or(&true));
+        }
+        self.annotation_alleles.retain(|x| x != allele);
+        true
+    }
+
+    /// Number of times an ALT allele was observed and of samples carrying
+    /// it, if genotypes or INFO/AC are available.
+    pub fn observations(&self, index: usize) -> (Option<u32>, Option<u32>) {
+        let count = self
+            .allele_count
+            .as_ref()
+            .and_then(|ac| ac.get(index).copied());
+        let carriers = self
+            .genotype_counts
+            .as_ref()
+            .and_then(|gc| gc.get(index))
+            .map(|gc| gc.het + gc.hom_alt);
+        (count, carriers)
+    }
+
+    /// Set id to the first 128 bits of the SHA-256 of the dataset, assembly,
+    /// contig, position, REF and ALT, in hex.
+    pub fn set_id(&mut self) {
+        let key = format!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.dataset_id,
+            self.assembly_id,
+            self.reference_name,
+            self.start,
+            self.reference_bases,
+            self.alternate_bases.join(",")
+        );
+        let digest = Sha256::digest(key.as_bytes());
+        self.id = Some(digest[..16].iter().map(|x| format!("{:02x}", x)).collect());
+    }
+
+    /// Split a variant with several ALT alleles into one variant per allele,
+    /// keeping the AF value and the ANN entries of that allele.
+    pub fn split(self) -> Vec<Variant> {
+        if self.alternate_bases.len() < 2 {
+            return vec![self];
+        }
+        let per_allele_af = self.allele_frequency.len() == self.alternate_bases.len();
+        self.alternate_bases
+            .iter()
+            .enumerate()
+            .map(|(i, alt)| {
+                let entries: Vec<usize> = (0..self.annotation_alleles.len())
+                    .filter(|&j| &self.annotation_alleles[j] == alt)
+                    .collect();
+                let select = |values: &Option<Vec<String>>| {
+                    values
+                        .as_ref()
+                        .map(|x| entries.iter().filter_map(|&j| x.get(j).cloned()).collect())
+                };
+                Variant {
+                    id: self.id.clone(),
+                    dataset_id: self.dataset_id.clone(),
+                    total_samples: self.total_samples,
+                    assembly_id: self.assembly_id.clone(),
+                    snp_ids: self.snp_ids.clone(),
+                    reference_name: self.reference_name.clone(),
+                    start: self.start,
+                    reference_bases: self.reference_bases.clone(),
+                    alternate_bases: vec![alt.clone()],
+                    vrs_ids: self.vrs_ids.get(i).cloned().into_iter().collect(),
+                    spdi: self.spdi.get(i).cloned().into_iter().collect(),
+                    gene_symbol: select(&self.gene_symbol),
+                    allele_frequency: if per_allele_af {
+                        vec![self.allele_frequency[i]]
+                    } else {
+                        self.allele_frequency.clone()
+                    },
+                    allele_count: self.allele_count.as_ref().map(|ac| match ac.get(i) {
+                        Some(&x) if ac.len() == self.alternate_bases.len() => vec![x],
+                        _ => ac.clone(),
+                    }),
+                    allele_number: self.allele_number,
+                    genotype_counts: self.genotype_counts.as_ref().map(|gc| match gc.get(i) {
+                        Some(x) if gc.len() == self.alternate_bases.len() => vec![x.clone()],
+                        _ => gc.clone(),
+                    }),
+                    call_rate: self.call_rate,
+                    sample_count: self.sample_count,
+                    coverage: self.coverage.clone(),
+                    genotype_quality: self.genotype_quality.clone(),
+                    format_distributions: self.format_distributions.clone(),
+                    scores: self.scores.clone(),
+                    mapped: self.mapped.clone(),
+                    sift: self.sift.clone(),
+                    polyphen: self.polyphen.clone(),
+                    clnsig: self.clnsig.clone(),
+                    clnsigconf: self.clnsigconf.clone(),
+                    clnrevstat: self.clnrevstat.clone(),
+                    clinvar_id: self.clinvar_id.clone(),
+                    gnomad: self.gnomad.clone(),
+                    hgvs: select(&self.hgvs),
+                    variant_type: select(&self.variant_type),
+                    sv_type: self.sv_type.clone(),
+                    end: self.end,
+                    sv_length: self.sv_length,
+                    mate: self.mate.clone(),
+                    annotation_alleles: entries.iter().map(|_| alt.clone()).collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+fn check_per_allele(field: &'static str, found: usize, expected: usize) -> Result<()> {
+    if found != expected {
+        return Err(Error::AlleleMismatch {
+            field,
+            found,
+            expected,
+        });
+    }
+    Ok(())
+}
+
+fn to_string(bytes: &[u8], field: &str) -> Result<String> {
+    str::from_utf8(bytes)
+        .map(|x| x.to_string())
+        .map_err(|source| Error::InvalidUtf8 {
+            field: field.to_string(),
+            source,
+        })
+}
+
+fn get_snp_ids(record: &Record) -> Result<Option<Vec<String>>> {
+    let id = to_string(&record.id(), "ID")?;
+    if id == "." {
+        return Ok(None);
+    }
+    Ok(Some(id.split(';').map(|x| x.to_string()).collect()))
+}
+
+pub fn get_reference_name(record: &Record) -> String {
+    record
+        .rid()
+        .and_then(|rid| record.header().rid2name(rid).ok())
+        .map(|x| String::from_utf8_lossy(x).to_string())
+        .unwrap_or_default()
+}
+
+/// Values of INFO/AF, or None if it is missing or not declared in the
+/// header, to fall back to the frequencies of the genotypes.
+fn get_allele_frequency(record: &Record) -> Result<Option<Vec<f32>>> {
+    if !has_info(record, "AF") {
+        return Ok(None);
+    }
+    Ok(record.info("AF".as_bytes()).float()?.map(|x| x.to_vec()))
+}
+
+/// Allele indices of the genotype of every sample, None for missing
+/// alleles, leaving out samples not in mask. None if the file has no GT
+/// field.
+fn get_genotypes(
+    record: &Record,
+    mask: Option<&[bool]>,
+) -> Result<Option<Vec<Vec<Option<usize>>>>> {
+    if record.header().format_type(GT.as_bytes()).is_err() {
+        return Ok(None);
+    }
+    let genotypes = record
+        .format(GT.as_bytes())
+        .integer()?
+        .iter()
+        .map(|sample| {
+            sample
+                .iter()
+                .take_while(|&&x| x != VECTOR_END)
+                // Alleles are encoded as (index + 1) << 1 | phased, 0 if missing.
+                .map(|&x| usize::try_from((x >> 1) - 1).ok())
+                .collect()
+        })
+        .collect();
+    Ok(Some(samples::select(genotypes, mask)))
+}
+
+/// Number of called alleles of every allele, REF first.
+fn allele_counts(genotypes: &[Vec<Option<usize>>], allele_count: usize) -> Vec<u32> {
+    let mut counts = vec![0; allele_count];
+    for index in genotypes.iter().flatten().flatten() {
+        if let Some(count) = counts.get_mut(*index) {
+            *count += 1;
+        }
+    }
+    counts
+}
+
+/// Fraction of samples whose genotype has no missing allele, None without
+/// samples.
+fn call_rate(genotypes: &[Vec<Option<usize>>]) -> Option<f64> {
+    if genotypes.is_empty() {
+        return None;
+    }
+    let called = genotypes
+        .iter()
+        .filter(|x| !x.is_empty() && x.iter().all(Option::is_some))
+        .count();
+    Some(called as f64 / genotypes.len() as f64)
+}
+
+/// Zygosity of the samples for every ALT allele, leaving out samples with a
+/// missing allele. Haploid calls count as homozygous.
+fn genotype_counts(genotypes: &[Vec<Option<usize>>], allele_count: usize) -> Vec<GenotypeCounts> {
+    (1..allele_count)
+        .map(|alt| {
+            let mut counts = GenotypeCounts::default();
+            for genotype in genotypes {
+                let called: Option<Vec<usize>> = genotype.iter().copied().collect();
+                let called = match called {
+                    Some(called) if !called.is_empty() => called,
+                    _ => continue,
+                };
+                if called.iter().all(|&x| x == 0) {
+                    counts.hom_ref += 1;
+                } else if called.iter().all(|&x| x == alt) {
+                    counts.hom_alt += 1;
+                } else if called.contains(&alt) {
+                    counts.het += 1;
+                }
+            }
+            counts.set_hwe();
+            counts
+        })
+        .collect()
+}
+
+/// Frequency of every ALT allele among called alleles, for files without
+/// INFO/AF. None if no allele was called.
+fn genotype_frequency(counts: &[u32]) -> Option<Vec<f32>> {
+    let total: u32 = counts.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    Some(
+        counts[1..]
+            .iter()
+            .map(|&x| x as f32 / total as f32)
+            .collect(),
+    )
+}
+
+/// Values of a numeric FORMAT field, Integer or Float, per sample in mask,
+/// leaving out missing values.
+pub fn format_values(record: &Record, tag: &str, mask: Option<&[bool]>) -> Result<Vec<Vec<f64>>> {
+    let is_float = matches!(
+        record.header().format_type(tag.as_bytes()),
+        Ok((TagType::Float, _))
+    );
+    let format = record.format(tag.as_bytes());
+    let values = if is_float {
+        format
+            .float()?
+            .iter()
+            .map(|sample| {
+                sample
+                    .iter()
+                    .take_while(|x| x.to_bits() != VECTOR_END_FLOAT)
+                    .filter(|x| !x.is_missing())
+                    .map(|&x| f64::from(x))
+                    .collect()
+            })
+            .collect()
+    } else {
+        format
+            .integer()?
+            .iter()
+            .map(|sample| {
+                sample
+                    .iter()
+                    .take_while(|&&x| x != VECTOR_END)
+                    .filter(|x| !x.is_missing())
+                    .map(|&x| f64::from(x))
+                    .collect()
+            })
+            .collect()
+    };
+    Ok(samples::select(values, mask))
+}
+
+/// First value of each INFO tag given with --score, leaving out tags that
+/// are missing. String tags are parsed as numbers, except SpliceAI
+/// (`ALLELE|SYMBOL|DS_AG|DS_AL|DS_DG|DS_DL|...`) whose score is the highest
+/// delta score.
+fn get_scores(record: &Record, tags: &[String]) -> Result<BTreeMap<String, f64>> {
+    let mut scores = BTreeMap::new();
+    for tag in tags {
+        let score = match record.header().info_type(tag.as_bytes()) {
+            Err(_) => None,
+            Ok((TagType::Float, _)) => record
+                .info(tag.as_bytes())
+                .float()?
+                .and_then(|x| x.iter().copied().find(|x| !x.is_missing()))
+                .map(f64::from),
+            Ok((TagType::Integer, _)) => record
+                .info(tag.as_bytes())
+                .integer()?
+                .and_then(|x| x.iter().copied().find(|x| !x.is_missing()))
+                .map(f64::from),
+            Ok(_) => get_info_field(record, tag)?
+                .and_then(|x| x.into_iter().next())
+                .and_then(|value| match tag.as_str() {
+                    "SpliceAI" => value
+                        .split('|')
+                        .skip(2)
+                        .take(4)
+                        .filter_map(|x| x.parse::<f64>().ok())
+                        .reduce(f64::max),
+                    _ => value.parse().ok(),
+                }),
+        };
+        if let Some(score) = score {
+            scores.insert(tag.clone(), score);
+        }
+    }
+    Ok(scores)
+}
+
+/// Distributions of the FORMAT fields given with --distribution, leaving
+/// out fields not declared in the header or without values.
+fn format_distributions(
+    record: &Record,
+    context: &Context,
+) -> Result<BTreeMap<String, FormatDistribution>> {
+    let mut distributions = BTreeMap::new();
+    for tag in &context.distributions {
+        if record.header().format_type(tag.as_bytes()).is_err() {
+            continue;
+        }
+        if let Some(distribution) = calc_distribution(record, tag, &[], context)? {
+            distributions.insert(tag.clone(), distribution);
+        }
+    }
+    Ok(distributions)
+}
+
+fn calc_distribution(
+    record: &Record,
+    tag: &str,
+    bins: &[f64],
+    context: &Context,
+) -> Result<Option<FormatDistribution>> {
+    let aggregation = context
+        .aggregations
+        .get(tag)
+        .copied()
+        .unwrap_or(Aggregation::All);
+    let values: Vec<f64> = format_values(record, tag, context.samples.as_deref())?
+        .into_iter()
+        .flat_map(|sample| match aggregation {
+            Aggregation::All => sample,
+            Aggregation::First => sample.into_iter().take(1).collect(),
+            Aggregation::Sum if sample.is_empty() => Vec::new(),
+            Aggregation::Sum => vec![sample.iter().sum()],
+        })
+        .collect();
+
+    // Statistics of no values are NaN, which is not valid JSON.
+    if values.is_empty() {
+        return Ok(None);
+    }
+    let histogram = (!bins.is_empty()).then(|| Histogram::new(bins, &values));
+    let mut data = Data::new(values);
+
+    Ok(Some(FormatDistribution {
+        min: data.min(),
+        q25: data.lower_quartile(),
+        median: data.median(),
+        q75: data.upper_quartile(),
+        max: data.max(),
+        mean: data.mean().unwrap_or(f64::NAN),
+        sd: data.std_dev().filter(|x| x.is_finite()),
+        percentiles: context
+            .percentiles
+            .iter()
+            .map(|&p| (format!("p{}", p), data.percentile(p)))
+            .collect(),
+        histogram,
+    }))
+}
+
+/// Sub-fields of every ANN entry of a record, none if it has no ANN.
+pub fn ann_entries(record: &Record) -> Result<Vec<Vec<String>>> {
+    let ann = get_ann(record)?.unwrap_or_default();
+    Ok(ann.iter().map(|x| split_ann(x)).collect())
+}
+
+/// ANN entries, or None if the record has none or the file is not
+/// annotated.
+fn get_ann(record: &Record) -> Result<Option<Vec<String>>> {
+    if !has_info(record, "ANN") {
+        return Ok(None);
+    }
+    get_info_field(record, "ANN")
+}
+
+fn split_ann(ann: &str) -> Vec<String> {
+    ann.split('|').map(|field| field.to_string()).collect()
+}
+
+fn get_field(fields: &[Vec<String>], index: usize) -> Result<Vec<String>> {
+    fields
+        .iter()
+        .map(|x| {
+            x.get(index).cloned().ok_or_else(|| Error::MalformedAnn {
+                index,
+                ann: x.join("|"),
+            })
+        })
+        .collect()
+}
+
+fn is_breakend(alt: &str) -> bool {
+    alt.contains('[') || alt.contains(']')
+}
+
+/// Mate position of a breakend ALT allele: `t[p[`, `t]p]`, `]p]t` or `[p[t`.
+fn parse_breakend(alt: &str) -> Result<Mate> {
+    let invalid = || Error::MalformedBreakend(alt.to_string());
+    let bracket = if alt.contains('[') { '[' } else { ']' };
+    let parts: Vec<&str> = alt.split(bracket).collect();
+    if parts.len() != 3 || (!parts[0].is_empty() && !parts[2].is_empty()) {
+        return Err(invalid());
+    }
+    let (contig, pos) = parts[1].rsplit_once(':').ok_or_else(invalid)?;
+    let contig = contig.trim_start_matches('<').trim_end_matches('>');
+    Ok(Mate {
+        reference_name: contig.to_string(),
+        start: pos.parse().map_err(|_| invalid())?,
+        id: None,
+    })
+}
+
+fn has_format(record: &Record, tag: &str) -> bool {
+    record.header().format_type(tag.as_bytes()).is_ok()
+}
+
+pub fn has_info(record: &Record, tag: &str) -> bool {
+    record.header().info_type(tag.as_bytes()).is_ok()
+}
+
+/// Values of an Integer INFO field, or None if it is missing or not
+/// declared in the header.
+fn get_info_integers(record: &Record, tag: &str) -> Result<Option<Vec<i32>>> {
+    if !has_info(record, tag) {
+        return Ok(None);
+    }
+    Ok(record.info(tag.as_bytes()).integer()?.map(|x| x.to_vec()))
+}
+
+/// First value of an Integer INFO field, or None if it is missing or not
+/// declared in the header.
+fn get_info_integer(record: &Record, tag: &str) -> Result<Option<i64>> {
+    if !has_info(record, tag) {
+        return Ok(None);
+    }
+    let values = record.info(tag.as_bytes()).integer()?;
+    Ok(values
+        .and_then(|x| x.first().copied())
+        .filter(|x| !x.is_missing())
+        .map(i64::from))
+}
+
+pub fn get_info_field(record: &Record, tag: &str) -> Result<Option<Vec<String>>> {
+    let info = match record.info(tag.as_bytes()).string()? {
+        Some(info) => info,
+        None => return Ok(None),
+    };
+    info.iter()
+        .map(|y| to_string(y, tag))
+        .collect::<Result<_>>()
+        .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_htslib::bcf::record::GenotypeAllele;
+    use rust_htslib::bcf::{Format, Header, Writer};
+
+    /// Record at 1:100 A>G of two samples with genotypes 0/1 and 1/1, with
+    /// INFO/AF declared in the header if given.
+    fn record(af: Option<f32>) -> Record {
+        let mut header = Header::new();
+        header.push_record(b"##contig=<ID=1,length=1000>");
+        header.push_record(br#"##FORMAT=<ID=GT,Number=1,Type=String,Description="Genotype">"#);
+        if af.is_some() {
+            header.push_record(
+                br#"##INFO=<ID=AF,Number=A,Type=Float,Description="Allele Frequency">"#,
+            );
+        }
+        header.push_sample(b"S1").push_sample(b"S2");
+        let dir = tempfile::tempdir().unwrap();
+        let writer =
+            Writer::from_path(dir.path().join("test.vcf"), &header, true, Format::Vcf).unwrap();
+        let mut record = writer.empty_record();
+
+        record.set_rid(Some(0));
+        record.set_pos(99);
+        record.set_alleles(&[b"A", b"G"]).unwrap();
+        record
+            .push_genotypes(&[
+                GenotypeAllele::Unphased(0),
+                GenotypeAllele::Unphased(1),
+                GenotypeAllele::Unphased(1),
+                GenotypeAllele::Unphased(1),
+            ])
+            .unwrap();
+        if let Some(af) = af {
+            record.push_info_float(b"AF", &[af]).unwrap();
+        }
+        record
+    }
+
+    #[test]
+    fn allele_frequency_is_read_from_af() {
+        let record = record(Some(0.25));
+        assert_eq!(get_allele_frequency(&record).unwrap(), Some(vec![0.25]));
+    }
+
+    #[test]
+    fn allele_frequency_falls_back_to_genotypes_without_af() {
+        let record = record(None);
+        assert_eq!(get_allele_frequency(&record).unwrap(), None);
+
+        let genotypes = get_genotypes(&record, None).unwrap().unwrap();
+        let counts = allele_counts(&genotypes, 2);
+        assert_eq!(counts, vec![1, 3]);
+        assert_eq!(genotype_frequency(&counts), Some(vec![0.75]));
+    }
+
+    fn mate(alt: &str) -> Option<(String, i64)> {
+        parse_breakend(alt)
+            .ok()
+            .map(|mate| (mate.reference_name, mate.start))
+    }
+
+    #[test]
+    fn breakend_mate_is_parsed_for_every_orientation() {
+        assert_eq!(mate("N[chr5:123["), Some(("chr5".to_string(), 123)));
+        assert_eq!(mate("N]chr5:123]"), Some(("chr5".to_string(), 123)));
+        assert_eq!(mate("]13:123456]T"), Some(("13".to_string(), 123456)));
+        assert_eq!(
+            mate("[HLA-A*01:01:1:100[G"),
+            Some(("HLA-A*01:01:1".to_string(), 100))
+        );
+        assert_eq!(mate("C[<ctg1>:7["), Some(("ctg1".to_string(), 7)));
+    }
+
+    #[test]
+    fn malformed_breakends_are_rejected() {
+        assert_eq!(mate("N[chr5:123"), None);
+        assert_eq!(mate("N[chr5:123[T"), None);
+        assert_eq!(mate("N[chr5["), None);
+        assert_eq!(mate("N[chr5:x["), None);
+        assert_eq!(mate("<DEL>"), None);
+    }
+}
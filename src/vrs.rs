@@ -0,0 +1,224 @@
+use crate::error::{Error, Result};
+use crate::fasta::Fasta;
+use crate::variant::Variant;
+use sha2::{Digest, Sha512};
+use std::collections::HashMap;
+
+/// Bases hashed at once when computing the digest of a whole contig.
+const CHUNK: u64 = 1 << 20;
+
+/// GA4GH VRS 1.3 computed identifiers of the ALT alleles of variants, with
+/// the digests of the reference contigs computed once.
+pub struct Vrs {
+    digests: HashMap<String, String>,
+}
+
+impl Vrs {
+    pub fn new() -> Self {
+        Vrs {
+            digests: HashMap::new(),
+        }
+    }
+
+    /// Set `vrs_ids`, one per ALT allele, unless an allele is symbolic.
+    pub fn annotate(&mut self, v: &mut Variant, fasta: &Fasta) -> Result<()> {
+        if !is_sequence(&v.reference_bases) || !v.alternate_bases.iter().all(|x| is_sequence(x)) {
+            return Ok(());
+        }
+        let unknown = || Error::UnknownContig(v.reference_name.clone());
+        let len = fasta.len(&v.reference_name).ok_or_else(unknown)?;
+        if !self.digests.contains_key(&v.reference_name) {
+            let digest = sequence_digest(fasta, &v.reference_name, len).ok_or_else(unknown)?;
+            self.digests.insert(v.reference_name.clone(), digest);
+        }
+        let sequence_id = &self.digests[&v.reference_name];
+
+        let start = v.start as u64 - 1;
+        let reference = v.reference_bases.to_ascii_uppercase().into_bytes();
+        v.vrs_ids = v
+            .alternate_bases
+            .iter()
+            .map(|alt| {
+                let alt = alt.to_ascii_uppercase().into_bytes();
+                let (start, end, state) =
+                    normalize(fasta, &v.reference_name, len, start, &reference, &alt)
+                        .ok_or_else(unknown)?;
+                Ok(allele_id(sequence_id, start, end, &state))
+            })
+            .collect::<Result<_>>()?;
+        Ok(())
+    }
+}
+
+/// Whether an allele is made of bases only, not symbolic.
+pub fn is_sequence(allele: &str) -> bool {
+    allele.bytes().all(|b| b"ACGTNacgtn".contains(&b))
+}
+
+/// `ga4gh:VA.` identifier of an allele replacing the interbase interval
+/// start..end of a sequence with state.
+fn allele_id(sequence_id: &str, start: u64, end: u64, state: &[u8]) -> String {
+    // Canonical JSON: sorted keys, no spaces, identifiers reduced to digests.
+    let location = format!(
+        r#"{{"interval":{{"end":{{"type":"Number","value":{}}},"start":{{"type":"Number","value":{}}},"type":"SequenceInterval"}},"sequence_id":"{}","type":"SequenceLocation"}}"#,
+        end, start, sequence_id
+    );
+    let allele = format!(
+        r#"{{"location":"{}","state":{{"sequence":"{}","type":"LiteralSequenceExpression"}},"type":"Allele"}}"#,
+        sha512t24u(location.as_bytes()),
+        String::from_utf8_lossy(state)
+    );
+    format!("ga4gh:VA.{}", sha512t24u(allele.as_bytes()))
+}
+
+/// First 24 bytes of the SHA-512 digest, in URL-safe base64.
+fn sha512t24u(blob: &[u8]) -> String {
+    let digest = Sha512::digest(blob);
+    base64::encode_config(&digest[..24], base64::URL_SAFE)
+}
+
+/// Digest of the uppercase sequence of a contig, its `ga4gh:SQ.` identifier
+/// without the prefix.
+fn sequence_digest(fasta: &Fasta, contig: &str, len: u64) -> Option<String> {
+    let mut hasher = Sha512::new();
+    let mut start = 0;
+    while start < len {
+        let end = (start + CHUNK).min(len);
+        hasher.update(fasta.fetch(contig, start, end - 1)?.to_ascii_uppercase());
+        start = end;
+    }
+    let digest = hasher.finalize();
+    Some(base64::encode_config(&digest[..24], base64::URL_SAFE))
+}
+
+/// Fully justified normalization of VRS: trim bases shared by REF and ALT,
+/// then expand indels over the whole region where they could be placed.
+/// Returns the interbase interval and the ALT sequence over it, which
+/// canonical SPDI uses too.
+pub fn normalize(
+    fasta: &Fasta,
+    contig: &str,
+    len: u64,
+    start: u64,
+    reference: &[u8],
+    alt: &[u8],
+) -> Option<(u64, u64, Vec<u8>)> {
+    let suffix = reference
+        .iter()
+        .rev()
+        .zip(alt.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (reference, alt) = (
+        &reference[..reference.len() - suffix],
+        &alt[..alt.len() - suffix],
+    );
+    let prefix = reference
+        .iter()
+        .zip(alt)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (reference, alt) = (&reference[prefix..], &alt[prefix..]);
+    let start = start + prefix as u64;
+    let end = start + reference.len() as u64;
+    if reference.is_empty() == alt.is_empty() {
+        return Some((start, end, alt.to_vec()));
+    }
+
+    // Fetch more of the reference until both rolls stop inside it.
+    let alleles = [reference, alt];
+    let mut margin = 128;
+    loop {
+        let lo = start.saturating_sub(margin);
+        let hi = (end + margin).min(len);
+        let seq = fasta
+            .fetch(contig, lo, hi.max(lo + 1) - 1)?
+            .to_ascii_uppercase();
+        let left = roll_left(&seq, &alleles, (start - lo) as usize);
+        let right = roll_right(&seq, &alleles, (end - lo) as usize);
+        if (lo > 0 && left as u64 == start - lo) || (hi < len && right as u64 == hi - end) {
+            margin *= 4;
+            continue;
+        }
+        let before = &seq[(start - lo) as usize - left..(start - lo) as usize];
+        let after = &seq[(end - lo) as usize..(end - lo) as usize + right];
+        let state = [before, alt, after].concat();
+        return Some((start - left as u64, end + right as u64, state));
+    }
+}
+
+/// Number of bases the alleles can be shifted left from pos, rotating them.
+fn roll_left(seq: &[u8], alleles: &[&[u8]], pos: usize) -> usize {
+    let mut d = 0;
+    while d < pos
+        && alleles
+            .iter()
+            .all(|a| a.is_empty() || a[a.len() - 1 - d % a.len()] == seq[pos - d - 1])
+    {
+        d += 1;
+    }
+    d
+}
+
+/// Number of bases the alleles can be shifted right from pos.
+fn roll_right(seq: &[u8], alleles: &[&[u8]], pos: usize) -> usize {
+    let mut d = 0;
+    while pos + d < seq.len()
+        && alleles
+            .iter()
+            .all(|a| a.is_empty() || a[d % a.len()] == seq[pos + d])
+    {
+        d += 1;
+    }
+    d
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// Contig 1 is ACGTACGTTTTTGCA, with a run of T from 7 to 11, 0-based.
+    fn fasta() -> (TempDir, Fasta) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ref.fa");
+        fs::write(&path, ">1\nACGTACGTTTTTGCA\n>2\nacgt\n").unwrap();
+        let fasta = Fasta::open(&path).unwrap();
+        (dir, fasta)
+    }
+
+    #[test]
+    fn digests_match_the_ga4gh_test_vectors() {
+        assert_eq!(sha512t24u(b""), "z4PhNX7vuL3xVChQ1m2AB9Yg5AULVxXc");
+        assert_eq!(sha512t24u(b"ACGT"), "aKF498dAxcJAqme6QYQ7EZ07-fiw8Kw2");
+        let (_dir, fasta) = fasta();
+        assert_eq!(
+            sequence_digest(&fasta, "2", 4).unwrap(),
+            "aKF498dAxcJAqme6QYQ7EZ07-fiw8Kw2"
+        );
+    }
+
+    #[test]
+    fn allele_id_matches_published_identifier() {
+        // rs7412 (APOE), NC_000019.10:g.44908822C>T.
+        assert_eq!(
+            allele_id("IIB53T8CNeJJdUqzn9V_JnRtQadwWCbl", 44908821, 44908822, b"T"),
+            "ga4gh:VA.CxiA_hvYbkD8Vqwjhx5AYuyul4mtlkpD"
+        );
+    }
+
+    #[test]
+    fn indels_are_expanded_over_the_repeat() {
+        let (_dir, fasta) = fasta();
+        let normalize = |start, reference: &[u8], alt: &[u8]| {
+            normalize(&fasta, "1", 15, start, reference, alt).unwrap()
+        };
+        // Substitutions only lose shared bases.
+        assert_eq!(normalize(4, b"ACG", b"ATG"), (5, 6, b"T".to_vec()));
+        // One T deleted or inserted anywhere in the run of five.
+        assert_eq!(normalize(10, b"TT", b"T"), (7, 12, b"TTTT".to_vec()));
+        assert_eq!(normalize(7, b"T", b"TT"), (7, 12, b"TTTTTT".to_vec()));
+        assert_eq!(normalize(6, b"GT", b"G"), (7, 12, b"TTTT".to_vec()));
+    }
+}
@@ -0,0 +1,180 @@
+use crate::cli::WatchOpts;
+use glob::Pattern;
+use log::{error, info, warn};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitStatus};
+use std::thread;
+use std::time::Duration;
+
+/// Dataset and assembly of a file to import.
+struct Target {
+    dataset: String,
+    assembly: String,
+}
+
+/// Import files appearing in a directory, one `import` process per file,
+/// and move them to the done or failed directory afterwards. A file is
+/// imported once its size is the same on two scans in a row, so files still
+/// being copied are left alone.
+pub fn run(opts: WatchOpts) {
+    let pattern = Pattern::new(&opts.pattern)
+        .unwrap_or_else(|e| panic!("Invalid pattern {}: {}", opts.pattern, e));
+    let manifest = opts
+        .manifest
+        .as_deref()
+        .map(read_manifest)
+        .unwrap_or_default();
+    let done = opts
+        .done_dir
+        .clone()
+        .unwrap_or_else(|| opts.dir.join("done"));
+    let failed = opts
+        .failed_dir
+        .clone()
+        .unwrap_or_else(|| opts.dir.join("failed"));
+    for dir in [&done, &failed] {
+        fs::create_dir_all(dir).unwrap_or_else(|e| panic!("Error creating {:?}: {}", dir, e));
+    }
+    info!("Watching {:?} for {}", opts.dir, opts.pattern);
+    let mut sizes: HashMap<PathBuf, u64> = HashMap::new();
+    loop {
+        let mut seen = HashMap::new();
+        for path in scan(&opts.dir, &pattern) {
+            let size = match fs::metadata(&path) {
+                Ok(metadata) => metadata.len(),
+                Err(_) => continue,
+            };
+            if sizes.get(&path) != Some(&size) {
+                seen.insert(path, size);
+                continue;
+            }
+
+            let target = match target(&path, &manifest) {
+                Some(target) => target,
+                None => {
+                    warn!(
+                        "No dataset for {:?}, add it to the manifest or name it dataset.assembly.vcf.gz",
+                        path
+                    );
+                    move_to(&path, &failed);
+                    continue;
+                }
+            };
+            info!(
+                "Importing {:?} into dataset {} ({})",
+                path, target.dataset, target.assembly
+            );
+            let status = import(&target.dataset, &target.assembly, &opts.import_args, &path);
+            match status {
+                Ok(status) if status.success() => {
+                    info!("Imported {:?}", path);
+                    move_to(&path, &done);
+                }
+                Ok(status) => {
+                    error!("Import of {:?} failed with {}", path, status);
+                    move_to(&path, &failed);
+                }
+                Err(e) => {
+                    error!("Can't run import of {:?}: {}", path, e);
+                    move_to(&path, &failed);
+                }
+            }
+        }
+        sizes = seen;
+        thread::sleep(Duration::from_secs(opts.interval));
+    }
+}
+
+/// Import a file in a separate `import` process, so that a failed import
+/// doesn't stop the caller.
+pub fn import(
+    dataset: &str,
+    assembly: &str,
+    args: &[String],
+    path: &Path,
+) -> io::Result<ExitStatus> {
+    let exe = env::current_exe()?;
+    process::Command::new(exe)
+        .arg("import")
+        .args(["--dataset", dataset])
+        .args(["--assembly", assembly])
+        .args(args)
+        .arg(path)
+        .status()
+}
+
+/// Files of the directory matching the pattern, oldest first.
+fn scan(dir: &Path, pattern: &Pattern) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Error reading {:?}: {}", dir, e);
+            return Vec::new();
+        }
+    };
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|x| x.is_file()))
+        .filter(|entry| pattern.matches(&entry.file_name().to_string_lossy()))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect();
+    files.sort();
+    files.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Dataset and assembly from the manifest, or from file names like
+/// `bipmed.hg38.vcf.gz`.
+fn target(path: &Path, manifest: &HashMap<String, (String, String)>) -> Option<Target> {
+    let name = path.file_name()?.to_string_lossy();
+    if let Some((dataset, assembly)) = manifest.get(name.as_ref()) {
+        return Some(Target {
+            dataset: dataset.clone(),
+            assembly: assembly.clone(),
+        });
+    }
+    let mut parts = name.split('.');
+    let dataset = parts.next().filter(|x| !x.is_empty())?;
+    let assembly = parts.next().filter(|x| !["vcf", "bcf"].contains(x))?;
+    Some(Target {
+        dataset: dataset.to_string(),
+        assembly: assembly.to_string(),
+    })
+}
+
+/// Read a file with three whitespace-separated columns: file name, dataset
+/// and assembly.
+fn read_manifest(path: &Path) -> HashMap<String, (String, String)> {
+    let content =
+        fs::read_to_string(path).unwrap_or_else(|e| panic!("Error reading {:?}: {}", path, e));
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(
+            |line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [file, dataset, assembly] => (
+                    file.to_string(),
+                    (dataset.to_string(), assembly.to_string()),
+                ),
+                _ => panic!("Invalid manifest line in {:?}: {}", path, line),
+            },
+        )
+        .collect()
+}
+
+/// Move a file and its index, if any, to a directory.
+fn move_to(path: &Path, dir: &Path) {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    for suffix in ["", ".tbi", ".csi"] {
+        let from = path.with_file_name(format!("{}{}", name, suffix));
+        if suffix.is_empty() || from.exists() {
+            let to = dir.join(format!("{}{}", name, suffix));
+            if let Err(e) = fs::rename(&from, &to) {
+                error!("Error moving {:?} to {:?}: {}", from, to, e);
+            }
+        }
+    }
+}